@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+fn bench_codec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("codec");
+    for size in [64, 4096, 1 << 20] {
+        let data = (0..size).map(|n| (n * 31 + 7) as u8).collect::<Vec<_>>();
+        let encoded = base131072::encode(&data);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("encode", size), &data, |b, data| {
+            b.iter(|| base131072::encode(data))
+        });
+        group.bench_with_input(BenchmarkId::new("decode", size), &encoded, |b, encoded| {
+            b.iter(|| base131072::decode(encoded).unwrap())
+        });
+        // the same block packing with the binary search of the ranges, the baseline of the page
+        // tables used by `encode` and `decode`
+        group.bench_with_input(
+            BenchmarkId::new("binary_search_encode", size),
+            &data,
+            |b, data| b.iter(|| base131072::__private::binary_search_encode(data)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("binary_search_decode", size),
+            &encoded,
+            |b, encoded| b.iter(|| base131072::__private::binary_search_decode(encoded).unwrap()),
+        );
+        #[cfg(feature = "rayon")]
+        {
+            group.bench_with_input(BenchmarkId::new("par_encode", size), &data, |b, data| {
+                b.iter(|| base131072::par_encode(data))
+            });
+            group.bench_with_input(
+                BenchmarkId::new("par_decode", size),
+                &encoded,
+                |b, encoded| b.iter(|| base131072::par_decode(encoded).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_codec);
+criterion_main!(benches);
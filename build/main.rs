@@ -6,6 +6,11 @@ use std::process::Command;
 
 const LOOKUP_TABLE_SIZE: usize = (1 << 17) + 2;
 
+const PAGE_BITS: u32 = 8;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+const INVALID: u16 = u16::MAX;
+const CHECKPOINT_INTERVAL: usize = 32;
+
 const OUT_FILE: &str = "src/lookup_table.rs";
 
 const UNICODE_DATA_PATH: &str = "ucd/UnicodeData.txt";
@@ -116,6 +121,79 @@ impl<'a> Iterator for Ranges<'a> {
     }
 }
 
+/// Split the symbol values into pages, returning `(code_point_base, block)` for every page and
+/// the blocks of code point deltas. Pages with contiguous code points share the identity block.
+fn encode_pages(code_points: &[u32]) -> (Vec<(u32, u16)>, Vec<Vec<u16>>) {
+    let identity = (0..PAGE_SIZE as u16).collect::<Vec<_>>();
+    let mut blocks = vec![identity];
+    let pages = code_points
+        .chunks(PAGE_SIZE)
+        .map(|page| {
+            let base = page[0];
+            let deltas = page
+                .iter()
+                .map(|code_point| (code_point - base) as u16)
+                .collect::<Vec<_>>();
+            (base, block_index(&mut blocks, deltas))
+        })
+        .collect();
+    (pages, blocks)
+}
+
+/// Split the code points up to the last valid one into pages, returning `(value_base, block)`
+/// for every page and the blocks of value deltas, with `INVALID` marking invalid code points.
+/// Fully valid pages share the identity block and fully invalid pages share the invalid block.
+fn decode_pages(code_points: &[u32]) -> (Vec<(u32, u16)>, Vec<Vec<u16>>) {
+    let identity = (0..PAGE_SIZE as u16).collect::<Vec<_>>();
+    let invalid = vec![INVALID; PAGE_SIZE];
+    let mut blocks = vec![identity, invalid];
+    let page_count = (*code_points.last().unwrap() >> PAGE_BITS) as usize + 1;
+    let mut pages = Vec::with_capacity(page_count);
+    let mut value = 0;
+    for page in 0..page_count as u32 {
+        let base = value as u32;
+        let mut deltas = vec![INVALID; PAGE_SIZE];
+        while value < code_points.len() && code_points[value] >> PAGE_BITS == page {
+            deltas[(code_points[value] as usize) % PAGE_SIZE] = (value as u32 - base) as u16;
+            value += 1;
+        }
+        pages.push((base, block_index(&mut blocks, deltas)));
+    }
+    (pages, blocks)
+}
+
+/// Delta encode the ranges as `(gap_from_previous_range, length - 1)`
+fn compact_ranges(ranges: &[(u32, u32, u32)]) -> Vec<(u16, u16)> {
+    let mut next_start = 0;
+    ranges
+        .iter()
+        .map(|&(_, start, end)| {
+            let gap = u16::try_from(start - next_start).expect("range gap exceeds u16");
+            let len = u16::try_from(end - start).expect("range length exceeds u16");
+            next_start = end + 1;
+            (gap, len)
+        })
+        .collect()
+}
+
+/// The `(table_offset, range_start)` of every `CHECKPOINT_INTERVAL`th range
+fn checkpoints(ranges: &[(u32, u32, u32)]) -> Vec<(u32, u32)> {
+    ranges
+        .iter()
+        .step_by(CHECKPOINT_INTERVAL)
+        .map(|&(offset, start, _)| (offset, start))
+        .collect()
+}
+
+fn block_index(blocks: &mut Vec<Vec<u16>>, block: Vec<u16>) -> u16 {
+    if let Some(idx) = blocks.iter().position(|existing| *existing == block) {
+        idx as u16
+    } else {
+        blocks.push(block);
+        (blocks.len() - 1) as u16
+    }
+}
+
 fn main() -> io::Result<()> {
     if Path::new(OUT_FILE).exists() {
         return Ok(());
@@ -124,6 +202,15 @@ fn main() -> io::Result<()> {
     let pad1 = code_points.pop().unwrap();
     let pad2 = code_points.pop().unwrap();
     let code_point_ranges = Ranges::new(&code_points).collect::<Vec<_>>();
+    let (encode_pages, encode_blocks) = encode_pages(&code_points);
+    let (decode_pages, decode_blocks) = decode_pages(&code_points);
+    // separate arrays rather than tuples, so their layout is known to vectorized lookups
+    let (encode_page_bases, encode_page_blocks): (Vec<_>, Vec<_>) =
+        encode_pages.into_iter().unzip();
+    let (decode_page_bases, decode_page_blocks): (Vec<_>, Vec<_>) =
+        decode_pages.into_iter().unzip();
+    let compact_ranges = compact_ranges(&code_point_ranges);
+    let checkpoints = checkpoints(&code_point_ranges);
     let code = format!(
         r#"
 //! AUTOMATICALLY GENERATED! DO NOT EDIT
@@ -139,6 +226,41 @@ pub const PAD1: u32 = {pad1};
 pub const PAD2: u32 = {pad2};
 /// A Lookup Table with `(table_offset, range_start, range_end)` of valid unicode code points
 pub const LOOKUP_TABLE: &[(u32, u32, u32)] = &{code_point_ranges:?};
+
+/// The number of low bits of a symbol value or code point indexing into a page
+#[cfg(not(feature = "compact-tables"))]
+pub const PAGE_BITS: u32 = {PAGE_BITS};
+/// The delta marking an invalid code point in `DECODE_BLOCKS`
+#[cfg(not(feature = "compact-tables"))]
+pub const INVALID: u16 = {INVALID};
+/// The code point base of every page of symbol values
+#[cfg(not(feature = "compact-tables"))]
+pub const ENCODE_PAGE_BASES: &[u32] = &{encode_page_bases:?};
+/// The block of code point deltas of every page of symbol values
+#[cfg(not(feature = "compact-tables"))]
+pub const ENCODE_PAGE_BLOCKS: &[u16] = &{encode_page_blocks:?};
+/// Blocks of code point deltas from the page's code point base
+#[cfg(not(feature = "compact-tables"))]
+pub const ENCODE_BLOCKS: &[[u16; {PAGE_SIZE}]] = &{encode_blocks:?};
+/// The symbol value base of every page of code points up to the last valid one
+#[cfg(not(feature = "compact-tables"))]
+pub const DECODE_PAGE_BASES: &[u32] = &{decode_page_bases:?};
+/// The block of symbol value deltas of every page of code points up to the last valid one
+#[cfg(not(feature = "compact-tables"))]
+pub const DECODE_PAGE_BLOCKS: &[u16] = &{decode_page_blocks:?};
+/// Blocks of symbol value deltas from the page's symbol value base
+#[cfg(not(feature = "compact-tables"))]
+pub const DECODE_BLOCKS: &[[u16; {PAGE_SIZE}]] = &{decode_blocks:?};
+
+/// The ranges of `LOOKUP_TABLE` as `(gap_from_previous_range, length - 1)`
+#[cfg(feature = "compact-tables")]
+pub const COMPACT_RANGES: &[(u16, u16)] = &{compact_ranges:?};
+/// The number of ranges between checkpoints
+#[cfg(feature = "compact-tables")]
+pub const CHECKPOINT_INTERVAL: usize = {CHECKPOINT_INTERVAL};
+/// The `(table_offset, range_start)` of every `CHECKPOINT_INTERVAL`th range
+#[cfg(feature = "compact-tables")]
+pub const CHECKPOINTS: &[(u32, u32)] = &{checkpoints:?};
     "#
     );
     fs::write(OUT_FILE, code)?;
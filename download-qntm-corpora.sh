@@ -0,0 +1,20 @@
+#!/bin/sh
+
+set -ex
+
+DIR="testdata/qntm"
+
+copy_test_data()
+{
+rm -rf "$DIR/$1.git"
+git clone --depth 1 "https://github.com/qntm/$1" "$DIR/$1.git"
+mkdir -p "$DIR/$1/pairs" "$DIR/$1/bad"
+cp "$DIR/$1.git"/test-data/pairs/* "$DIR/$1/pairs/"
+cp "$DIR/$1.git"/test-data/bad/* "$DIR/$1/bad/"
+git -C "$DIR/$1.git" rev-parse HEAD
+rm -rf "$DIR/$1.git"
+}
+
+copy_test_data base65536
+copy_test_data base32768
+copy_test_data base2048
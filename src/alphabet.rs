@@ -0,0 +1,257 @@
+use crate::lookup_table::{LOOKUP_TABLE, PAD1, PAD2};
+use core::cmp::Ordering;
+
+/// The largest valid unicode code point
+const MAX_CODE_POINT: u32 = 0x10FFFF;
+/// The first UTF-16 surrogate code point
+const SURROGATE_START: u32 = 0xD800;
+/// The last UTF-16 surrogate code point
+const SURROGATE_END: u32 = 0xDFFF;
+/// Marker for a missing padding code point
+const NO_PADDING: u32 = u32::MAX;
+
+/// A repertoire of unicode code points used to represent symbol values
+///
+/// The code points are described by a table of `(table_offset, range_start, range_end)` ranges,
+/// the same format as the generated lookup table. Symbol value `n` is represented by the `n`th
+/// code point of the table. Alphabets used by [`BaseN`](crate::BaseN) also have two extra code
+/// points marking that the encoded data was padded by one or two bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Alphabet {
+    table: &'static [(u32, u32, u32)],
+    len: u32,
+    pad1: u32,
+    pad2: u32,
+}
+
+impl Alphabet {
+    /// The full repertoire of 2^17 code points used by base131072
+    pub const BASE131072: Self = Self::new(LOOKUP_TABLE).with_padding(PAD1, PAD2);
+    /// The first 2^16 code points of the base131072 repertoire
+    pub const BASE65536: Self = Self::BASE131072.truncate(1 << 16);
+    /// The first 2^15 code points of the base131072 repertoire
+    pub const BASE32768: Self = Self::BASE131072.truncate(1 << 15);
+    /// The first 2^11 code points of the base131072 repertoire
+    pub const BASE2048: Self = Self::BASE131072.truncate(1 << 11);
+
+    /// Create an alphabet without padding from a table of `(table_offset, range_start, range_end)`
+    /// ranges
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ranges are not sorted, overlapping, do not have contiguous offsets or contain
+    /// anything but valid unicode scalar values. When used in a `const` this is a compile time
+    /// error.
+    pub const fn new(table: &'static [(u32, u32, u32)]) -> Self {
+        assert!(!table.is_empty(), "alphabet table is empty");
+        let mut idx = 0;
+        let mut len = 0;
+        while idx < table.len() {
+            let (offset, start, end) = table[idx];
+            assert!(offset == len, "alphabet table offsets are not contiguous");
+            assert!(start <= end, "alphabet table range is reversed");
+            assert!(
+                end <= MAX_CODE_POINT,
+                "alphabet table range exceeds U+10FFFF"
+            );
+            assert!(
+                end < SURROGATE_START || start > SURROGATE_END,
+                "alphabet table range contains surrogates"
+            );
+            if idx > 0 {
+                assert!(
+                    start > table[idx - 1].2,
+                    "alphabet table ranges are not sorted"
+                );
+            }
+            len += end - start + 1;
+            idx += 1;
+        }
+        Self {
+            table,
+            len,
+            pad1: NO_PADDING,
+            pad2: NO_PADDING,
+        }
+    }
+
+    /// Set the code points marking that the encoded data was padded by one or two bytes
+    ///
+    /// # Panics
+    ///
+    /// Panics if the padding code points are equal, not valid unicode scalar values or part of the
+    /// alphabet table
+    pub const fn with_padding(self, pad1: u32, pad2: u32) -> Self {
+        assert!(
+            is_scalar_value(pad1) && is_scalar_value(pad2) && pad1 != pad2,
+            "alphabet padding is not a pair of distinct unicode scalar values"
+        );
+        assert!(
+            !table_contains(self.table, pad1) && !table_contains(self.table, pad2),
+            "alphabet padding is part of the alphabet table"
+        );
+        Self { pad1, pad2, ..self }
+    }
+
+    /// Restrict the alphabet to its first `len` code points
+    ///
+    /// The two code points following the kept symbols become the padding code points.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alphabet does not have at least `len + 2` code points
+    pub const fn truncate(self, len: u32) -> Self {
+        assert!(len + 2 <= self.len, "alphabet is too small to truncate");
+        Self {
+            table: self.table,
+            len,
+            pad1: table_code_point(self.table, len + 1),
+            pad2: table_code_point(self.table, len),
+        }
+    }
+
+    /// The number of symbols in the alphabet, not including padding
+    #[allow(clippy::len_without_is_empty)]
+    pub const fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Whether the alphabet has padding code points
+    pub const fn has_padding(&self) -> bool {
+        self.pad1 != NO_PADDING
+    }
+
+    /// Convert a symbol value to its code point, if the value is part of the alphabet
+    pub fn encode(&self, value: u32) -> Option<char> {
+        if value >= self.len {
+            return None;
+        }
+        char::from_u32(table_code_point(self.table, value))
+    }
+
+    /// Convert a code point to its symbol value, if the code point is part of the alphabet
+    pub fn decode(&self, ch: char) -> Option<u32> {
+        let code_point = ch as u32;
+        let lookup_idx = self
+            .table
+            .binary_search_by(|&(_, start, stop)| {
+                if start > code_point {
+                    Ordering::Greater
+                } else if code_point > stop {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .ok()?;
+        let (idx, start, _) = self.table[lookup_idx];
+        let value = code_point - start + idx;
+        if value < self.len {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// The code point marking that the encoded data was padded by `bytes` bytes
+    ///
+    /// Returns `None` if `bytes` is not 1 or 2 or if the alphabet has no padding
+    pub fn padding(&self, bytes: usize) -> Option<char> {
+        match bytes {
+            1 => char::from_u32(self.pad1),
+            2 => char::from_u32(self.pad2),
+            _ => None,
+        }
+    }
+
+    /// The number of padding bytes marked by a code point, if it is a padding code point
+    pub fn decode_padding(&self, ch: char) -> Option<usize> {
+        match ch as u32 {
+            code_point if code_point == self.pad1 => Some(1),
+            code_point if code_point == self.pad2 => Some(2),
+            _ => None,
+        }
+    }
+}
+
+const fn is_scalar_value(code_point: u32) -> bool {
+    code_point <= MAX_CODE_POINT && (code_point < SURROGATE_START || code_point > SURROGATE_END)
+}
+
+const fn table_contains(table: &[(u32, u32, u32)], code_point: u32) -> bool {
+    let mut idx = 0;
+    while idx < table.len() {
+        if table[idx].1 <= code_point && code_point <= table[idx].2 {
+            return true;
+        }
+        idx += 1;
+    }
+    false
+}
+
+/// Find the code point of a symbol value in a table. The value must be in range of the table.
+const fn table_code_point(table: &[(u32, u32, u32)], value: u32) -> u32 {
+    let mut low = 0;
+    let mut high = table.len();
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if table[mid].0 <= value {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let (idx, start, _) = table[low];
+    value - idx + start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alphabet_encoding() {
+        for alphabet in [
+            Alphabet::BASE2048,
+            Alphabet::BASE32768,
+            Alphabet::BASE65536,
+            Alphabet::BASE131072,
+        ] {
+            for value in (0..alphabet.len()).step_by(7).chain([alphabet.len() - 1]) {
+                let ch = alphabet.encode(value).unwrap();
+                assert_eq!(alphabet.decode(ch), Some(value));
+                assert_eq!(alphabet.decode_padding(ch), None);
+            }
+            assert_eq!(alphabet.encode(alphabet.len()), None);
+            for bytes in [1, 2] {
+                let pad = alphabet.padding(bytes).unwrap();
+                assert_eq!(alphabet.decode(pad), None);
+                assert_eq!(alphabet.decode_padding(pad), Some(bytes));
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_padding() {
+        let alphabet = Alphabet::BASE131072;
+        let truncated = alphabet.truncate(1 << 11);
+        assert_eq!(truncated.padding(2), alphabet.encode(1 << 11));
+        assert_eq!(truncated.padding(1), alphabet.encode((1 << 11) + 1));
+        assert_eq!(
+            Alphabet::BASE131072.padding(1),
+            char::from_u32(crate::lookup_table::PAD1)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "alphabet padding is part of the alphabet table")]
+    fn invalid_padding() {
+        Alphabet::new(&[(0, 65, 70)]).with_padding(48, 70);
+    }
+
+    #[test]
+    #[should_panic(expected = "alphabet table offsets are not contiguous")]
+    fn invalid_table() {
+        Alphabet::new(&[(0, 65, 70), (7, 97, 102)]);
+    }
+}
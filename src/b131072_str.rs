@@ -0,0 +1,368 @@
+//! Strings which are known to be valid base131072, so they are validated once instead of by every
+//! function they are passed to
+
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+use core::borrow::Borrow;
+use core::fmt;
+use core::ops::Deref;
+use core::str::{Chars, FromStr};
+
+#[cfg(feature = "forbid-unsafe")]
+use ref_cast::{ref_cast_custom, RefCastCustom};
+
+use crate::lookup_table::{PAD1, PAD2};
+use crate::stream::{ByteReader, Decoder};
+use crate::{encode, symbols_byte_len, InvalidChar, B17};
+
+/// A borrowed base131072 encoded string, which is known to decode without errors
+///
+/// This is to [`B131072String`] what `str` is to `String`.
+///
+/// ```
+/// use base131072::B131072Str;
+///
+/// let encoded = base131072::encode(b"hello world");
+/// let string = B131072Str::new(&encoded).unwrap();
+/// let mut bytes = [0; 11];
+/// assert_eq!(string.decoded_len(), 11);
+/// string.decode_into(&mut bytes);
+/// assert_eq!(&bytes, b"hello world");
+/// assert!(B131072Str::new("hello\n").is_err());
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "forbid-unsafe", derive(RefCastCustom))]
+#[repr(transparent)]
+pub struct B131072Str(str);
+
+impl B131072Str {
+    /// Check that a string is valid base131072, failing with the same error as
+    /// [`decode`](crate::decode) if it is not
+    pub fn new(string: &str) -> Result<&Self, InvalidChar> {
+        validate(string)?;
+        Ok(Self::from_str_unchecked(string))
+    }
+
+    /// Reference a string without checking it, which is private so only validated strings are
+    /// referenced
+    #[cfg(not(feature = "forbid-unsafe"))]
+    fn from_str_unchecked(string: &str) -> &Self {
+        // SAFETY: `B131072Str` is a transparent wrapper of `str`
+        unsafe { &*(string as *const str as *const Self) }
+    }
+
+    /// Reference a string without checking it, which is private so only validated strings are
+    /// referenced
+    #[cfg(feature = "forbid-unsafe")]
+    #[ref_cast_custom]
+    fn from_str_unchecked(string: &str) -> &Self;
+
+    /// The encoded string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The number of bytes the string decodes to
+    pub fn decoded_len(&self) -> usize {
+        let padding = match self.0.chars().next_back().map(|ch| ch as u32) {
+            Some(PAD1) => 1,
+            Some(PAD2) => 2,
+            _ => 0,
+        };
+        let symbols = self.0.chars().count() - (padding > 0) as usize;
+        symbols_byte_len(symbols) - padding
+    }
+
+    /// Decode the string into the start of `out`, returning the number of bytes written
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` is shorter than [`decoded_len`](Self::decoded_len).
+    pub fn decode_into(&self, out: &mut [u8]) -> usize {
+        let mut len = 0;
+        self.decode_with(|bytes| {
+            out[len..len + bytes.len()].copy_from_slice(bytes);
+            len += bytes.len();
+        });
+        len
+    }
+
+    /// Decode the string, passing the bytes to `f` a block at a time
+    pub(crate) fn decode_with(&self, mut f: impl FnMut(&[u8])) {
+        let mut decoder = Decoder::new();
+        for ch in self.0.chars() {
+            if let Some(block) = decoder.push(ch).expect("validated on construction") {
+                f(&block);
+            }
+        }
+        let (tail, len) = decoder.finish().expect("validated on construction");
+        f(&tail[..len]);
+    }
+
+    /// Decode the string
+    pub fn decode(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.decoded_len()];
+        self.decode_into(&mut bytes);
+        bytes
+    }
+
+    /// The 17 bit values of the symbols of the string, without the padding
+    pub fn symbols(&self) -> Symbols<'_> {
+        Symbols(self.0.chars())
+    }
+
+    /// The decoded bytes of the string, which are decoded a block at a time as they are iterated
+    pub fn bytes(&self) -> DecodedBytes<'_> {
+        DecodedBytes(ByteReader::new(self.0.chars()))
+    }
+
+    /// Copy the string into an owned [`B131072String`]
+    pub fn to_b131072_string(&self) -> B131072String {
+        B131072String(self.0.into())
+    }
+}
+
+/// Validate all the chars like `decode_chars`, without decoding them
+fn validate(string: &str) -> Result<(), InvalidChar> {
+    let mut padding = None;
+    let mut symbols = 0;
+    for (idx, ch) in string.chars().enumerate() {
+        if let Some((pad_idx, pad)) = padding {
+            return Err(InvalidChar(pad_idx, pad));
+        }
+        if matches!(ch as u32, PAD1 | PAD2) {
+            padding = Some((idx, ch));
+        } else if B17::decode(ch).is_none() {
+            return Err(InvalidChar(idx, ch));
+        } else {
+            symbols += 1;
+        }
+    }
+    match padding {
+        // padding removes at most 2 of the at least 3 bytes of any symbols
+        Some((pad_idx, pad)) if symbols == 0 => Err(InvalidChar(pad_idx, pad)),
+        _ => Ok(()),
+    }
+}
+
+impl AsRef<str> for B131072Str {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<B131072Str> for B131072Str {
+    fn as_ref(&self) -> &B131072Str {
+        self
+    }
+}
+
+impl ToOwned for B131072Str {
+    type Owned = B131072String;
+
+    fn to_owned(&self) -> B131072String {
+        self.to_b131072_string()
+    }
+}
+
+impl fmt::Display for B131072Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for &'a B131072Str {
+    type Error = InvalidChar;
+
+    fn try_from(string: &'a str) -> Result<Self, InvalidChar> {
+        B131072Str::new(string)
+    }
+}
+
+impl PartialEq<str> for B131072Str {
+    fn eq(&self, other: &str) -> bool {
+        &self.0 == other
+    }
+}
+
+/// The symbol values of a [`B131072Str`], returned by [`B131072Str::symbols`]
+#[derive(Debug, Clone)]
+pub struct Symbols<'a>(Chars<'a>);
+
+impl Iterator for Symbols<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        // the padding is the only char which is not a symbol, and it can only come last
+        self.0.next().and_then(B17::decode).map(|b17| b17.0)
+    }
+}
+
+/// The decoded bytes of a [`B131072Str`], returned by [`B131072Str::bytes`]
+pub struct DecodedBytes<'a>(ByteReader<Chars<'a>>);
+
+impl Iterator for DecodedBytes<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0.next_byte().expect("validated on construction")
+    }
+}
+
+impl fmt::Debug for DecodedBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecodedBytes").finish_non_exhaustive()
+    }
+}
+
+/// An owned base131072 encoded string, which is known to decode without errors
+///
+/// ```
+/// use base131072::B131072String;
+///
+/// let string = B131072String::encode(b"hello world");
+/// assert_eq!(string.decode(), b"hello world");
+/// assert_eq!(string.as_str().parse::<B131072String>().unwrap(), string);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct B131072String(String);
+
+impl B131072String {
+    /// Check that a string is valid base131072, failing with the same error as
+    /// [`decode`](crate::decode) if it is not
+    pub fn new(string: String) -> Result<Self, InvalidChar> {
+        validate(&string)?;
+        Ok(Self(string))
+    }
+
+    /// Encode some bytes, which always results in a valid string
+    pub fn encode<T: AsRef<[u8]>>(input: T) -> Self {
+        Self(encode(input))
+    }
+
+    /// Borrow the string as a [`B131072Str`]
+    pub fn as_b131072_str(&self) -> &B131072Str {
+        B131072Str::from_str_unchecked(&self.0)
+    }
+
+    /// Unwrap the encoded string
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for B131072String {
+    type Target = B131072Str;
+
+    fn deref(&self) -> &B131072Str {
+        self.as_b131072_str()
+    }
+}
+
+impl Borrow<B131072Str> for B131072String {
+    fn borrow(&self) -> &B131072Str {
+        self.as_b131072_str()
+    }
+}
+
+impl AsRef<B131072Str> for B131072String {
+    fn as_ref(&self) -> &B131072Str {
+        self.as_b131072_str()
+    }
+}
+
+impl AsRef<str> for B131072String {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&B131072Str> for B131072String {
+    fn from(string: &B131072Str) -> Self {
+        string.to_b131072_string()
+    }
+}
+
+impl From<B131072String> for String {
+    fn from(string: B131072String) -> Self {
+        string.0
+    }
+}
+
+impl TryFrom<String> for B131072String {
+    type Error = InvalidChar;
+
+    fn try_from(string: String) -> Result<Self, InvalidChar> {
+        Self::new(string)
+    }
+}
+
+impl FromStr for B131072String {
+    type Err = InvalidChar;
+
+    fn from_str(string: &str) -> Result<Self, InvalidChar> {
+        B131072Str::new(string).map(B131072Str::to_b131072_string)
+    }
+}
+
+impl fmt::Display for B131072String {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::decode;
+    use crate::test_support::corrupted;
+    use std::borrow::Cow;
+
+    #[test]
+    fn matches_decode() {
+        let mut state = 0x3C6E_F372_FE94_F82B;
+        for len in 0..100 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = crate::encode(&input);
+            let string = B131072Str::new(&encoded).unwrap();
+            assert_eq!(string.decoded_len(), len);
+            assert_eq!(string.decode(), input);
+            assert_eq!(string.bytes().collect::<Vec<_>>(), input);
+            let mut out = vec![0xAA; len + 3];
+            assert_eq!(string.decode_into(&mut out), len);
+            assert_eq!(&out[..len], input);
+            assert!(string
+                .symbols()
+                .map(|value| B17(value).encode())
+                .eq(encoded.chars().filter(|&ch| B17::decode(ch).is_some())));
+
+            for corrupted in corrupted(&mut state, &encoded) {
+                match decode(&corrupted) {
+                    Ok(bytes) => assert_eq!(B131072Str::new(&corrupted).unwrap().decode(), bytes),
+                    Err(err) => assert_eq!(B131072Str::new(&corrupted), Err(err)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn decode_into_short_buffer() {
+        B131072String::encode([1; 20]).decode_into(&mut [0; 19]);
+    }
+
+    #[test]
+    fn owned() {
+        let owned = B131072String::encode("owned");
+        let borrowed: &B131072Str = &owned;
+        assert_eq!(borrowed.to_owned(), owned);
+        let cow: Cow<B131072Str> = Cow::Borrowed(borrowed);
+        assert_eq!(cow.into_owned().into_string(), crate::encode("owned"));
+        assert_eq!(owned.to_string(), crate::encode("owned"));
+        assert_eq!(B131072String::new("\n".into()), Err(InvalidChar(0, '\n')));
+        assert_eq!(B131072String::default().decode(), b"");
+    }
+}
@@ -0,0 +1,251 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::alphabet::Alphabet;
+use crate::InvalidChar;
+
+/// Iterator splitting bytes into `BITS`-bit symbol values, most significant bit first
+///
+/// The last symbol is filled up with zero bits.
+#[derive(Debug, Clone)]
+pub(crate) struct B8ToBnIter<'a, const BITS: u32> {
+    data: &'a [u8],
+    index: usize,
+    acc: u32,
+    acc_bits: u32,
+}
+
+impl<'a, const BITS: u32> B8ToBnIter<'a, BITS> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            index: 0,
+            acc: 0,
+            acc_bits: 0,
+        }
+    }
+}
+
+impl<'a, const BITS: u32> Iterator for B8ToBnIter<'a, BITS> {
+    type Item = u32;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.acc_bits < BITS {
+            if let Some(&byte) = self.data.get(self.index) {
+                self.index += 1;
+                self.acc = (self.acc << 8) | byte as u32;
+                self.acc_bits += 8;
+            } else if self.acc_bits == 0 {
+                return None;
+            } else {
+                let value = (self.acc << (BITS - self.acc_bits)) & mask(BITS);
+                self.acc = 0;
+                self.acc_bits = 0;
+                return Some(value);
+            }
+        }
+        self.acc_bits -= BITS;
+        let value = self.acc >> self.acc_bits;
+        self.acc &= mask(self.acc_bits);
+        Some(value)
+    }
+}
+
+/// Iterator joining `BITS`-bit symbol values back into bytes, most significant bit first
+///
+/// If the symbols do not end on a byte boundary, the left over bits are returned as a last byte
+/// filled up with zero bits.
+#[derive(Debug, Clone)]
+pub(crate) struct BnToB8Iter<I, const BITS: u32> {
+    symbols: I,
+    acc: u32,
+    acc_bits: u32,
+}
+
+impl<I, const BITS: u32> BnToB8Iter<I, BITS> {
+    pub(crate) fn new(symbols: I) -> Self {
+        Self {
+            symbols,
+            acc: 0,
+            acc_bits: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = u32>, const BITS: u32> Iterator for BnToB8Iter<I, BITS> {
+    type Item = u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.acc_bits < 8 {
+            if let Some(value) = self.symbols.next() {
+                self.acc = (self.acc << BITS) | value;
+                self.acc_bits += BITS;
+            } else if self.acc_bits == 0 {
+                return None;
+            } else {
+                let byte = (self.acc << (8 - self.acc_bits)) as u8;
+                self.acc = 0;
+                self.acc_bits = 0;
+                return Some(byte);
+            }
+        }
+        self.acc_bits -= 8;
+        let byte = (self.acc >> self.acc_bits) as u8;
+        self.acc &= mask(self.acc_bits);
+        Some(byte)
+    }
+}
+
+const fn mask(bits: u32) -> u32 {
+    (1 << bits) - 1
+}
+
+/// The number of bytes that encoding `byte_size` bytes with `bits`-bit symbols pads the data by
+pub(crate) const fn padding_len(bits: u32, byte_size: usize) -> usize {
+    let symbols = (byte_size * 8).div_ceil(bits as usize);
+    (symbols * bits as usize).div_ceil(8) - byte_size
+}
+
+/// An encoding packing `BITS` bits of data into every symbol of an [`Alphabet`]
+///
+/// `BITS` can be anything from 1 to 17. When the data does not fill up the last symbol, a
+/// padding code point is appended to record how many bytes the decoder should drop.
+#[derive(Debug, Clone, Copy)]
+pub struct BaseN<const BITS: u32> {
+    alphabet: Alphabet,
+}
+
+impl<const BITS: u32> BaseN<BITS> {
+    /// Create an encoding from an alphabet with at least `2^BITS` symbols
+    ///
+    /// Larger alphabets are [truncated](Alphabet::truncate) to `2^BITS` symbols.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `BITS` is not between 1 and 17, if the alphabet is too small or if an alphabet of
+    /// exactly `2^BITS` symbols has no padding
+    pub const fn new(alphabet: Alphabet) -> Self {
+        assert!(BITS >= 1 && BITS <= 17, "BaseN supports 1 to 17 bits");
+        assert!(alphabet.len() >= 1 << BITS, "alphabet is too small");
+        let alphabet = if alphabet.len() == 1 << BITS {
+            alphabet
+        } else {
+            alphabet.truncate(1 << BITS)
+        };
+        assert!(alphabet.has_padding(), "alphabet has no padding");
+        Self { alphabet }
+    }
+
+    /// The alphabet of the encoding
+    pub const fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// Encode some bytes to a string
+    pub fn encode<T: AsRef<[u8]>>(&self, input: T) -> String {
+        let input = input.as_ref();
+        let mut out = String::with_capacity(input.len() * 8 / BITS as usize);
+        for value in B8ToBnIter::<BITS>::new(input) {
+            // every value of `BITS` bits is part of the alphabet
+            out.push(self.alphabet.encode(value).unwrap());
+        }
+        if let Some(padding) = self.alphabet.padding(padding_len(BITS, input.len())) {
+            out.push(padding);
+        }
+        out
+    }
+
+    /// Decode an encoded string
+    pub fn decode<T: AsRef<str>>(&self, input: T) -> Result<Vec<u8>, InvalidChar> {
+        let mut string = input.as_ref();
+        let mut padding = None;
+        if let Some((last_char_index, ch)) = string.char_indices().last() {
+            if let Some(bytes) = self.alphabet.decode_padding(ch) {
+                padding = Some((bytes, ch));
+                string = &string[..last_char_index];
+            }
+        }
+        let mut invalid = None;
+        let symbols =
+            string
+                .chars()
+                .enumerate()
+                .map_while(|(idx, ch)| match self.alphabet.decode(ch) {
+                    Some(value) => Some(value),
+                    None => {
+                        invalid = Some(InvalidChar(idx, ch));
+                        None
+                    }
+                });
+        let mut bytes = BnToB8Iter::<_, BITS>::new(symbols).collect::<Vec<_>>();
+        if let Some(invalid) = invalid {
+            return Err(invalid);
+        }
+        if let Some((padding, ch)) = padding {
+            if padding > bytes.len() {
+                return Err(InvalidChar(string.chars().count(), ch));
+            }
+            bytes.truncate(bytes.len() - padding);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BASE131072, BASE2048, BASE32768, BASE65536};
+
+    #[test]
+    fn bit_packing() {
+        let data = (0..=255).collect::<Vec<u8>>();
+        for len in 0..data.len() {
+            let data = &data[..len];
+            let b11 = B8ToBnIter::<11>::new(data).collect::<Vec<_>>();
+            assert_eq!(b11.len(), (len * 8).div_ceil(11));
+            let mut bytes = BnToB8Iter::<_, 11>::new(b11.into_iter()).collect::<Vec<_>>();
+            assert_eq!(bytes.len() - padding_len(11, len), len);
+            bytes.truncate(len);
+            assert_eq!(bytes, data);
+        }
+        assert_eq!(
+            B8ToBnIter::<5>::new(&[0b1010_1010, 0b1100_0011]).collect::<Vec<_>>(),
+            vec![0b10101, 0b01011, 0b00001, 0b10000]
+        );
+    }
+
+    #[test]
+    fn test_padding_len() {
+        assert_eq!(padding_len(16, 0), 0);
+        assert_eq!(padding_len(16, 1), 1);
+        assert_eq!(padding_len(16, 2), 0);
+        assert_eq!(padding_len(11, 1), 1);
+        assert_eq!(padding_len(11, 3), 2);
+        assert_eq!(padding_len(11, 11), 0);
+        assert_eq!(padding_len(15, 2), 2);
+    }
+
+    #[test]
+    fn encoding() {
+        let data = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+        for len in [0, 1, 2, 3, 4, 11, 15, 16, 17, 100, 1000] {
+            let data = &data[..len];
+            assert_eq!(BASE2048.decode(BASE2048.encode(data)).unwrap(), data);
+            assert_eq!(BASE32768.decode(BASE32768.encode(data)).unwrap(), data);
+            assert_eq!(BASE65536.decode(BASE65536.encode(data)).unwrap(), data);
+            assert_eq!(BASE131072.decode(BASE131072.encode(data)).unwrap(), data);
+            assert_eq!(BASE131072.encode(data), crate::encode(data));
+        }
+    }
+
+    #[test]
+    fn invalid_chars() {
+        let encoded = BASE2048.encode([1, 2, 3]);
+        assert_eq!(encoded.chars().count(), 4);
+        let pad = encoded.chars().last().unwrap();
+        assert!(matches!(BASE2048.decode(format!("{pad}")), Err(InvalidChar(0, ch)) if ch == pad));
+        let out_of_range = Alphabet::BASE131072.encode(1 << 12).unwrap();
+        assert!(matches!(
+            BASE2048.decode(format!("a{out_of_range}b")),
+            Err(InvalidChar(1, ch)) if ch == out_of_range
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+//! Conversion of whole blocks of 17 bytes to 8 symbols of 17 bits and back
+//!
+//! The first 16 bytes of a block are loaded into a `u128`, so every symbol can be extracted with
+//! a fixed shift instead of going through the bit accumulator of the iterators.
+
+/// The number of bytes in a block
+pub(crate) const BLOCK_BYTES: usize = 17;
+/// The number of symbols in a block
+pub(crate) const BLOCK_SYMBOLS: usize = 8;
+
+const MASK: u128 = (1 << 17) - 1;
+
+/// Split a block into its symbol values, most significant bit first
+#[inline]
+pub(crate) fn encode_block(block: &[u8; BLOCK_BYTES]) -> [u32; BLOCK_SYMBOLS] {
+    let (head, last) = block.split_at(16);
+    let head = u128::from_be_bytes(head.try_into().unwrap());
+    [
+        (head >> 111) as u32,
+        ((head >> 94) & MASK) as u32,
+        ((head >> 77) & MASK) as u32,
+        ((head >> 60) & MASK) as u32,
+        ((head >> 43) & MASK) as u32,
+        ((head >> 26) & MASK) as u32,
+        ((head >> 9) & MASK) as u32,
+        (((head & 0x1FF) as u32) << 8) | last[0] as u32,
+    ]
+}
+
+/// Join the symbol values of a block back into bytes. Every value must be less than 2^17.
+#[inline]
+pub(crate) fn decode_block(values: &[u32; BLOCK_SYMBOLS]) -> [u8; BLOCK_BYTES] {
+    let head = (values[0] as u128) << 111
+        | (values[1] as u128) << 94
+        | (values[2] as u128) << 77
+        | (values[3] as u128) << 60
+        | (values[4] as u128) << 43
+        | (values[5] as u128) << 26
+        | (values[6] as u128) << 9
+        | (values[7] >> 8) as u128;
+    let mut block = [0; BLOCK_BYTES];
+    block[..16].copy_from_slice(&head.to_be_bytes());
+    block[16] = values[7] as u8;
+    block
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::base_n::{B8ToBnIter, BnToB8Iter};
+
+    /// xorshift64, good enough to fuzz the bit shuffling without extra dependencies
+    pub(crate) fn random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn matches_iterators() {
+        let mut state = 0x2545_F491_4F6C_DD1D;
+        for _ in 0..10_000 {
+            let mut block = [0; BLOCK_BYTES];
+            block.fill_with(|| random(&mut state) as u8);
+            let values = encode_block(&block);
+            assert!(values.iter().copied().eq(B8ToBnIter::<17>::new(&block)));
+            assert_eq!(decode_block(&values), block);
+
+            let mut values = [0; BLOCK_SYMBOLS];
+            values.fill_with(|| random(&mut state) as u32 & MASK as u32);
+            let block = decode_block(&values);
+            assert!(block
+                .iter()
+                .copied()
+                .eq(BnToB8Iter::<_, 17>::new(values.iter().copied())));
+            assert_eq!(encode_block(&block), values);
+        }
+        assert_eq!(encode_block(&[0xFF; BLOCK_BYTES]), [0x1FFFF; BLOCK_SYMBOLS]);
+    }
+}
@@ -0,0 +1,184 @@
+//! Encoding and decoding into the fixed capacity strings and vectors of `heapless` and `arrayvec`,
+//! for `no_std` callers without an allocator
+//!
+//! An output is either complete or not produced at all. The encoders push chars until the output
+//! is full, and only then work out the length the error reports, while the decoders check the
+//! decoded length before anything is written.
+
+use crate::{encode_chars, utf8_len, B131072Str, CapacityError, DecodeError};
+
+/// Push the chars of the encoding of some bytes until `push` fails for an output of `capacity`
+/// bytes of UTF-8, which the caller then drops
+fn push_chars(
+    input: &[u8],
+    capacity: usize,
+    mut push: impl FnMut(char) -> bool,
+) -> Result<(), CapacityError> {
+    for ch in encode_chars(input) {
+        if !push(ch) {
+            let required = utf8_len(input);
+            return Err(CapacityError { required, capacity });
+        }
+    }
+    Ok(())
+}
+
+/// Validate a string and check that it decodes to at most `capacity` bytes
+fn check_decoded_len(input: &str, capacity: usize) -> Result<&B131072Str, DecodeError> {
+    let string = B131072Str::new(input)?;
+    if string.decoded_len() > capacity {
+        return Err(DecodeError::OutputTooLarge { max: capacity });
+    }
+    Ok(string)
+}
+
+/// Encode some bytes to a string holding at most `N` bytes of UTF-8
+///
+/// Fails with a [`CapacityError`] if the encoding takes more than `N` bytes.
+///
+/// ```
+/// use base131072::{encode_heapless, CapacityError};
+///
+/// let encoded = encode_heapless::<64>(b"hello world").unwrap();
+/// assert_eq!(encoded, *base131072::encode(b"hello world"));
+/// assert_eq!(
+///     encode_heapless::<8>(b"hello world"),
+///     Err(CapacityError { required: 26, capacity: 8 })
+/// );
+/// ```
+#[cfg(feature = "heapless")]
+pub fn encode_heapless<const N: usize>(input: &[u8]) -> Result<heapless::String<N>, CapacityError> {
+    let mut out = heapless::String::new();
+    push_chars(input, N, |ch| out.push(ch).is_ok())?;
+    Ok(out)
+}
+
+/// Decode a base131072 encoded string to a vector holding at most `N` bytes
+///
+/// Fails with [`DecodeError::OutputTooLarge`] if the string decodes to more than `N` bytes, or with
+/// the same error as [`decode`](crate::decode) if it is invalid.
+#[cfg(feature = "heapless")]
+pub fn decode_heapless<const N: usize>(input: &str) -> Result<heapless::Vec<u8, N>, DecodeError> {
+    let string = check_decoded_len(input, N)?;
+    let mut out = heapless::Vec::new();
+    string.decode_with(|bytes| out.extend_from_slice(bytes).expect("checked the capacity"));
+    Ok(out)
+}
+
+/// Encode some bytes to a string holding at most `N` bytes of UTF-8
+///
+/// Fails with a [`CapacityError`] if the encoding takes more than `N` bytes.
+///
+/// ```
+/// use base131072::{encode_arrayvec, CapacityError};
+///
+/// let encoded = encode_arrayvec::<64>(b"hello world").unwrap();
+/// assert_eq!(encoded.as_str(), base131072::encode(b"hello world"));
+/// assert_eq!(
+///     encode_arrayvec::<8>(b"hello world"),
+///     Err(CapacityError { required: 26, capacity: 8 })
+/// );
+/// ```
+#[cfg(feature = "arrayvec")]
+pub fn encode_arrayvec<const N: usize>(
+    input: &[u8],
+) -> Result<arrayvec::ArrayString<N>, CapacityError> {
+    let mut out = arrayvec::ArrayString::new();
+    push_chars(input, N, |ch| out.try_push(ch).is_ok())?;
+    Ok(out)
+}
+
+/// Decode a base131072 encoded string to a vector holding at most `N` bytes
+///
+/// Fails with [`DecodeError::OutputTooLarge`] if the string decodes to more than `N` bytes, or with
+/// the same error as [`decode`](crate::decode) if it is invalid.
+#[cfg(feature = "arrayvec")]
+pub fn decode_arrayvec<const N: usize>(
+    input: &str,
+) -> Result<arrayvec::ArrayVec<u8, N>, DecodeError> {
+    let string = check_decoded_len(input, N)?;
+    let mut out = arrayvec::ArrayVec::new();
+    string.decode_with(|bytes| {
+        out.try_extend_from_slice(bytes)
+            .expect("checked the capacity")
+    });
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::{encode, InvalidChar, B17};
+
+    #[test]
+    fn exact_capacity() {
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+        for len in 0..120 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = encode(&input);
+            assert_eq!(utf8_len(&input), encoded.len());
+            assert!(encode_chars(&input).eq(encoded.chars()));
+            let mut out = String::new();
+            let fits = |out: &mut String, capacity| {
+                out.clear();
+                push_chars(&input, capacity, |ch| {
+                    let fits = out.len() + ch.len_utf8() <= capacity;
+                    if fits {
+                        out.push(ch);
+                    }
+                    fits
+                })
+            };
+            assert_eq!(fits(&mut out, encoded.len()), Ok(()));
+            assert_eq!(out, encoded);
+            if !encoded.is_empty() {
+                assert_eq!(
+                    fits(&mut out, encoded.len() - 1),
+                    Err(CapacityError {
+                        required: encoded.len(),
+                        capacity: encoded.len() - 1
+                    })
+                );
+            }
+        }
+        for value in 0..1 << 17 {
+            assert_eq!(B17(value).utf8_width(), B17(value).encode().len_utf8());
+        }
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn heapless() {
+        let encoded = encode_heapless::<512>(&[9; 100]).unwrap();
+        assert_eq!(encoded, *encode([9; 100]));
+        assert_eq!(decode_heapless::<100>(&encoded).unwrap(), [9; 100]);
+        assert_eq!(
+            decode_heapless::<99>(&encoded),
+            Err(DecodeError::OutputTooLarge { max: 99 })
+        );
+        assert_eq!(
+            decode_heapless::<8>("\n"),
+            Err(DecodeError::InvalidChar(InvalidChar(0, '\n')))
+        );
+        assert!(encode_heapless::<0>(&[]).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "arrayvec")]
+    #[test]
+    fn arrayvec() {
+        let encoded = encode_arrayvec::<512>(&[9; 100]).unwrap();
+        assert_eq!(encoded.as_str(), encode([9; 100]));
+        assert_eq!(decode_arrayvec::<100>(&encoded).unwrap()[..], [9; 100]);
+        assert_eq!(
+            decode_arrayvec::<99>(&encoded),
+            Err(DecodeError::OutputTooLarge { max: 99 })
+        );
+        assert_eq!(
+            decode_arrayvec::<8>("\n"),
+            Err(DecodeError::InvalidChar(InvalidChar(0, '\n')))
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Encoding from and decoding into the buffers of `bytes`, a chunk at a time so non-contiguous
+//! input is never collected first
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use crate::stream::{Encoder, Utf8Decoder};
+use crate::{min_decoded_len, DecodeError};
+
+/// Encode the remaining bytes of a buffer, appending the UTF-8 of the encoding to `out`
+///
+/// The input is read a chunk at a time, so chained buffers are encoded without copying them
+/// together first.
+///
+/// ```
+/// use bytes::{Buf, BytesMut};
+///
+/// let input = (&b"hello "[..]).chain(&b"world"[..]);
+/// let mut out = BytesMut::new();
+/// base131072::encode_buf(input, &mut out);
+/// assert_eq!(out, base131072::encode(b"hello world").as_bytes());
+/// ```
+pub fn encode_buf<B: Buf, O: BufMut>(mut input: B, out: &mut O) {
+    let mut encoder = Encoder::new();
+    let mut put = |ch: char| out.put_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+    while input.has_remaining() {
+        let chunk = input.chunk();
+        let len = chunk.len();
+        encoder.update_chars(chunk, &mut put);
+        input.advance(len);
+    }
+    encoder.finish_chars(put);
+}
+
+/// Decode the remaining base131072 encoded UTF-8 bytes of a buffer, appending the decoded bytes
+/// to `out`
+///
+/// Chars may be split between the chunks of the input. Fails with the same error as
+/// [`decode_utf8_bytes`](crate::decode_utf8_bytes), in which case `out` is truncated back to its
+/// original length.
+///
+/// ```
+/// use bytes::{Buf, BytesMut};
+///
+/// let encoded = base131072::encode(b"hello world");
+/// let (first, second) = encoded.as_bytes().split_at(5);
+/// let mut out = BytesMut::new();
+/// base131072::decode_buf(first.chain(second), &mut out).unwrap();
+/// assert_eq!(out, &b"hello world"[..]);
+/// ```
+pub fn decode_buf<B: Buf>(mut input: B, out: &mut BytesMut) -> Result<(), DecodeError> {
+    let start = out.len();
+    let res = decode_chunks(&mut input, out);
+    if res.is_err() {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut out[start..]);
+        out.truncate(start);
+    }
+    res
+}
+
+fn decode_chunks<B: Buf>(input: &mut B, out: &mut BytesMut) -> Result<(), DecodeError> {
+    out.reserve(min_decoded_len(input.remaining()));
+    let mut decoder = Utf8Decoder::new();
+    while input.has_remaining() {
+        let chunk = input.chunk();
+        let len = chunk.len();
+        decoder.update(chunk, |bytes| out.extend_from_slice(bytes))?;
+        input.advance(len);
+    }
+    decoder.finish(|bytes| out.extend_from_slice(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::test_support::corrupted_bytes;
+    use crate::{decode_utf8_bytes, encode};
+    use bytes::Bytes;
+
+    /// Split some bytes into chunks of random lengths, chained into one buffer
+    fn chunked(state: &mut u64, bytes: &[u8]) -> impl Buf {
+        let mut chunks = Vec::new();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let (chunk, next) = rest.split_at(random(state) as usize % 10 % (rest.len() + 1));
+            chunks.push(Bytes::copy_from_slice(chunk));
+            rest = next;
+        }
+        chunks
+            .into_iter()
+            .fold(Box::new(Bytes::new()) as Box<dyn Buf>, |buf, chunk| {
+                Box::new(buf.chain(chunk))
+            })
+    }
+
+    #[test]
+    fn encoding() {
+        let mut state = 0x243F_6A88_85A3_08D3;
+        for len in 0..100 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let mut out = BytesMut::new();
+            encode_buf(chunked(&mut state, &input), &mut out);
+            assert_eq!(out, encode(&input).as_bytes());
+        }
+    }
+
+    #[test]
+    fn decoding() {
+        let mut state = 0x1319_8A2E_0370_7344;
+        for len in 0..100 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = encode(&input).into_bytes();
+            let mut out = BytesMut::from(&b"prefix"[..]);
+            decode_buf(chunked(&mut state, &encoded), &mut out).unwrap();
+            assert_eq!(out[..6], *b"prefix");
+            assert_eq!(out[6..], input);
+
+            for corrupted in corrupted_bytes(&mut state, &encoded) {
+                let mut out = BytesMut::from(&b"prefix"[..]);
+                match decode_utf8_bytes(&corrupted) {
+                    Ok(bytes) => {
+                        decode_buf(chunked(&mut state, &corrupted), &mut out).unwrap();
+                        assert_eq!(out[6..], bytes);
+                    }
+                    Err(err) => {
+                        assert_eq!(
+                            decode_buf(chunked(&mut state, &corrupted), &mut out),
+                            Err(err)
+                        );
+                        assert_eq!(out, &b"prefix"[..]);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! Encoding and decoding in `const` contexts, which walks the ranges of the alphabet instead of
+//! the page tables and splits the UTF-8 by hand, as iterators and traits are not available there
+
+use crate::base_n::padding_len;
+use crate::lookup_table::{LOOKUP_TABLE, PAD1, PAD2};
+use crate::{symbols_byte_len, DecodeError, InvalidChar};
+
+/// The code point of a symbol value, found by a binary search through the ranges
+const fn encode_value(value: u32) -> u32 {
+    let mut low = 0;
+    let mut high = LOOKUP_TABLE.len();
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        if value < LOOKUP_TABLE[mid].0 {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    let (offset, start, _) = LOOKUP_TABLE[low];
+    start + value - offset
+}
+
+/// The first symbol value whose code point is at least `code_point`, or 2^17 if there is none
+#[cfg(any(feature = "zeroize", feature = "heapless", feature = "arrayvec"))]
+pub(crate) const fn first_value_from(code_point: u32) -> u32 {
+    let mut idx = 0;
+    while idx < LOOKUP_TABLE.len() {
+        let (offset, start, end) = LOOKUP_TABLE[idx];
+        if code_point <= end {
+            return if code_point <= start {
+                offset
+            } else {
+                offset + code_point - start
+            };
+        }
+        idx += 1;
+    }
+    1 << 17
+}
+
+/// The symbol value of a code point, found by a binary search through the ranges
+pub(crate) const fn decode_code_point(code_point: u32) -> Option<u32> {
+    let mut low = 0;
+    let mut high = LOOKUP_TABLE.len();
+    while low < high {
+        let mid = (low + high) / 2;
+        let (offset, start, end) = LOOKUP_TABLE[mid];
+        if code_point < start {
+            high = mid;
+        } else if code_point > end {
+            low = mid + 1;
+        } else {
+            return Some(offset + code_point - start);
+        }
+    }
+    None
+}
+
+/// The code point of the char starting at `idx` of valid UTF-8, and its width
+const fn next_char(bytes: &[u8], idx: usize) -> (u32, usize) {
+    let lead = bytes[idx] as u32;
+    if lead < 0x80 {
+        (lead, 1)
+    } else if lead < 0xE0 {
+        ((lead & 0x1F) << 6 | payload(bytes, idx + 1), 2)
+    } else if lead < 0xF0 {
+        let rest = payload(bytes, idx + 1) << 6 | payload(bytes, idx + 2);
+        ((lead & 0x0F) << 12 | rest, 3)
+    } else {
+        let rest =
+            payload(bytes, idx + 1) << 12 | payload(bytes, idx + 2) << 6 | payload(bytes, idx + 3);
+        ((lead & 0x07) << 18 | rest, 4)
+    }
+}
+
+/// The 6 payload bits of a continuation byte
+const fn payload(bytes: &[u8], idx: usize) -> u32 {
+    (bytes[idx] & 0x3F) as u32
+}
+
+/// The chars being decoded, as UTF-8 or an array of chars
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Utf8(&'a [u8]),
+    Chars(&'a [char]),
+}
+
+impl Source<'_> {
+    const fn len(self) -> usize {
+        match self {
+            Self::Utf8(bytes) => bytes.len(),
+            Self::Chars(chars) => chars.len(),
+        }
+    }
+
+    /// The code point at a byte or char index, and the index of the next one
+    const fn next(self, idx: usize) -> (u32, usize) {
+        match self {
+            Self::Utf8(bytes) => {
+                let (code_point, width) = next_char(bytes, idx);
+                (code_point, idx + width)
+            }
+            Self::Chars(chars) => (chars[idx] as u32, idx + 1),
+        }
+    }
+}
+
+const fn char_of(code_point: u32) -> char {
+    match char::from_u32(code_point) {
+        Some(ch) => ch,
+        None => unreachable!(),
+    }
+}
+
+const fn invalid_char(idx: usize, code_point: u32) -> InvalidChar {
+    InvalidChar(idx, char_of(code_point))
+}
+
+/// The number of bytes a base131072 encoded string decodes to, failing with the same error as
+/// [`decode`](crate::decode) if it is invalid
+pub const fn decoded_len(string: &str) -> Result<usize, InvalidChar> {
+    source_decoded_len(Source::Utf8(string.as_bytes()))
+}
+
+const fn source_decoded_len(source: Source) -> Result<usize, InvalidChar> {
+    let mut idx = 0;
+    let mut chars = 0;
+    let mut padding = None;
+    while idx < source.len() {
+        let (code_point, next) = source.next(idx);
+        if let Some((pad_idx, pad)) = padding {
+            return Err(invalid_char(pad_idx, pad));
+        }
+        if code_point == PAD1 || code_point == PAD2 {
+            padding = Some((chars, code_point));
+        } else if decode_code_point(code_point).is_none() {
+            return Err(invalid_char(chars, code_point));
+        }
+        chars += 1;
+        idx = next;
+    }
+    match padding {
+        Some((pad_idx, pad)) => {
+            let len = symbols_byte_len(chars - 1);
+            let padding = if pad == PAD1 { 1 } else { 2 };
+            if padding > len {
+                return Err(invalid_char(pad_idx, pad));
+            }
+            Ok(len - padding)
+        }
+        None => Ok(symbols_byte_len(chars)),
+    }
+}
+
+/// Decode a base131072 encoded string which decodes to exactly `N` bytes
+///
+/// Fails with [`DecodeError::WrongLength`] if it decodes to a different number of bytes, or with
+/// the same error as [`decode`](crate::decode) if it is invalid.
+pub const fn decode_str<const N: usize>(string: &str) -> Result<[u8; N], DecodeError> {
+    decode_source(Source::Utf8(string.as_bytes()))
+}
+
+/// Decode base131072 encoded chars which decode to exactly `M` bytes, in a `const` context
+///
+/// Fails with [`DecodeError::WrongLength`] if they decode to a different number of bytes, or with
+/// the same error as [`decode`](crate::decode) if they are invalid.
+///
+/// ```
+/// use base131072::{decode_array, encode_array, encoded_chars};
+///
+/// const CHARS: [char; encoded_chars(4)] = encode_array(b"1234");
+/// const BYTES: [u8; 4] = match decode_array(&CHARS) {
+///     Ok(bytes) => bytes,
+///     Err(_) => panic!("invalid"),
+/// };
+/// assert_eq!(&BYTES, b"1234");
+/// ```
+pub const fn decode_array<const N: usize, const M: usize>(
+    input: &[char; N],
+) -> Result<[u8; M], DecodeError> {
+    decode_source(Source::Chars(input))
+}
+
+const fn decode_source<const N: usize>(source: Source) -> Result<[u8; N], DecodeError> {
+    match source_decoded_len(source) {
+        Ok(len) if len != N => {
+            return Err(DecodeError::WrongLength {
+                expected: N,
+                actual: len,
+            })
+        }
+        Ok(_) => {}
+        Err(err) => return Err(DecodeError::InvalidChar(err)),
+    }
+    let mut out = [0; N];
+    let mut written = 0;
+    let mut acc = 0u32;
+    let mut acc_bits = 0;
+    let mut idx = 0;
+    while idx < source.len() {
+        let (code_point, next) = source.next(idx);
+        idx = next;
+        let value = match decode_code_point(code_point) {
+            Some(value) => value,
+            // the padding, which is the last char
+            None => break,
+        };
+        acc = acc << 17 | value;
+        acc_bits += 17;
+        while acc_bits >= 8 {
+            acc_bits -= 8;
+            // the bytes removed by the padding are dropped
+            if written < N {
+                out[written] = (acc >> acc_bits) as u8;
+                written += 1;
+            }
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+    if acc_bits > 0 && written < N {
+        out[written] = (acc << (8 - acc_bits)) as u8;
+    }
+    Ok(out)
+}
+
+/// The number of chars `byte_size` bytes encode to, including the padding
+pub const fn encoded_chars(byte_size: usize) -> usize {
+    (byte_size * 8).div_ceil(17) + (padding_len(17, byte_size) > 0) as usize
+}
+
+/// Encode some bytes to an array of chars in a `const` context, which needs no allocation
+///
+/// The output is the same as that of [`encode`](crate::encode). `M` must be
+/// [`encoded_chars(N)`](encoded_chars), which fails to compile in `const` items otherwise.
+///
+/// ```
+/// use base131072::{encode_array, encoded_chars};
+///
+/// const ID: [char; encoded_chars(16)] = encode_array(&[0x42; 16]);
+/// assert_eq!(ID.iter().collect::<String>(), base131072::encode([0x42; 16]));
+/// ```
+pub const fn encode_array<const N: usize, const M: usize>(input: &[u8; N]) -> [char; M] {
+    assert!(
+        M == encoded_chars(N),
+        "M is not the number of chars N bytes encode to"
+    );
+    let mut out = ['\0'; M];
+    let mut written = 0;
+    let mut acc = 0u32;
+    let mut acc_bits = 0;
+    let mut idx = 0;
+    while idx < N {
+        acc = acc << 8 | input[idx] as u32;
+        acc_bits += 8;
+        if acc_bits >= 17 {
+            acc_bits -= 17;
+            out[written] = char_of(encode_value(acc >> acc_bits));
+            written += 1;
+            acc &= (1 << acc_bits) - 1;
+        }
+        idx += 1;
+    }
+    // the left over bits are filled up with zero bits
+    if acc_bits > 0 {
+        out[written] = char_of(encode_value(acc << (17 - acc_bits)));
+        written += 1;
+    }
+    match padding_len(17, N) {
+        1 => out[written] = char_of(PAD1),
+        2 => out[written] = char_of(PAD2),
+        _ => {}
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::test_support::corrupted;
+    use crate::{decode, encode, B17};
+
+    #[test]
+    fn matches_lookup() {
+        for code_point in (0..0x11_0000).step_by(3) {
+            if let Some(ch) = char::from_u32(code_point) {
+                let value = B17::decode(ch).map(|b17| b17.0);
+                assert_eq!(decode_code_point(code_point), value);
+                if let Some(value) = value {
+                    assert_eq!(encode_value(value), code_point);
+                    #[cfg(any(feature = "zeroize", feature = "heapless", feature = "arrayvec"))]
+                    {
+                        assert_eq!(first_value_from(code_point), value);
+                        assert_eq!(first_value_from(code_point + 1), value + 1);
+                    }
+                }
+                let mut utf8 = [0; 4];
+                assert_eq!(
+                    next_char(ch.encode_utf8(&mut utf8).as_bytes(), 0),
+                    (code_point, ch.len_utf8())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_decode() {
+        let mut state = 0x510E_527F_ADE6_82D1;
+        for len in 0..40 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = encode(&input);
+            assert_eq!(decoded_len(&encoded), Ok(len));
+            assert_eq!(encoded_chars(len), encoded.chars().count());
+
+            for corrupted in corrupted(&mut state, &encoded) {
+                assert_eq!(
+                    decoded_len(&corrupted),
+                    decode(&corrupted).map(|bytes| bytes.len())
+                );
+                let chars = corrupted.chars().collect::<Vec<_>>();
+                assert_eq!(
+                    source_decoded_len(Source::Chars(&chars)),
+                    decoded_len(&corrupted)
+                );
+            }
+        }
+        macro_rules! check_sizes {
+            ($($size:literal)*) => {$(
+                let input = core::array::from_fn::<u8, $size, _>(|_| random(&mut state) as u8);
+                assert_eq!(decode_str::<$size>(&encode(input)), Ok(input));
+                let chars = encode_array::<$size, { encoded_chars($size) }>(&input);
+                assert_eq!(chars.iter().collect::<String>(), encode(input));
+                assert_eq!(decode_array(&chars), Ok(input));
+                assert_eq!(
+                    decode_str::<{ $size + 1 }>(&encode(input)),
+                    Err(DecodeError::WrongLength {
+                        expected: $size + 1,
+                        actual: $size
+                    })
+                );
+            )*};
+        }
+        check_sizes!(0 1 2 3 4 7 8 15 16 17 18 33 34 35 100);
+    }
+}
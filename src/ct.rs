@@ -0,0 +1,380 @@
+//! Constant-time encoding and decoding, for secret material like keys and tokens
+//!
+//! The regular lookups go through tables indexed by the symbol value or code point, so which
+//! cache lines they touch depends on the data. These instead scan every range of the alphabet and
+//! select the matching one with masks, and split the UTF-8 and gather the symbols of the chars
+//! without branching or indexing on their widths. Only the lengths of the input and output, and
+//! whether it is valid, are observable.
+//!
+//! This is orders of magnitude slower than [`encode`](crate::encode) and
+//! [`decode`](crate::decode), so should only be used for small secrets.
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::lookup_table::{LOOKUP_TABLE, PAD1, PAD2};
+use crate::utf8::{into_string, push_utf8};
+use crate::{b17s, calc_padding, extend_from_b17s, InvalidChar, Padding, B17};
+
+/// The UTF-8 encoding of `PAD1`
+const PAD1_UTF8: [u8; 4] = utf8_bytes(PAD1);
+/// The UTF-8 encoding of `PAD2`
+const PAD2_UTF8: [u8; 4] = utf8_bytes(PAD2);
+
+const fn utf8_bytes(code_point: u32) -> [u8; 4] {
+    [
+        0xF0 | (code_point >> 18) as u8,
+        0x80 | (code_point >> 12 & 0x3F) as u8,
+        0x80 | (code_point >> 6 & 0x3F) as u8,
+        0x80 | (code_point & 0x3F) as u8,
+    ]
+}
+
+/// All ones if `a < b`, otherwise zero
+#[inline(always)]
+fn lt_mask(a: u32, b: u32) -> u32 {
+    (((a as u64).wrapping_sub(b as u64) >> 63) as u32).wrapping_neg()
+}
+
+/// The code point of a symbol value, touching every range of the alphabet
+fn encode_value(value: u32) -> u32 {
+    let mut code_point = 0;
+    for &(offset, start, end) in LOOKUP_TABLE {
+        let in_range = !lt_mask(value, offset) & lt_mask(value, offset + end - start + 1);
+        code_point |= in_range & start.wrapping_add(value.wrapping_sub(offset));
+    }
+    code_point
+}
+
+/// The symbol value of a code point and a mask of whether it is valid, touching every range of
+/// the alphabet
+fn decode_value(code_point: u32) -> (u32, u32) {
+    let mut value = 0;
+    let mut valid = 0;
+    for &(offset, start, end) in LOOKUP_TABLE {
+        let in_range = !lt_mask(code_point, start) & lt_mask(code_point, end + 1);
+        value |= in_range & offset.wrapping_add(code_point.wrapping_sub(start));
+        valid |= in_range;
+    }
+    (value, valid)
+}
+
+/// The code point of the UTF-8 sequence starting with the lead byte in `window`, which may be
+/// followed by bytes of the next sequences
+#[inline(always)]
+fn utf8_code_point(window: [u8; 4]) -> u32 {
+    let lead = window[0] as u32;
+    let width = 1 + (lead >= 0xC0) as u32 + (lead >= 0xE0) as u32 + (lead >= 0xF0) as u32;
+    let word = u32::from_be_bytes([
+        (lead & 0x7F >> width) as u8,
+        window[1],
+        window[2],
+        window[3],
+    ]) >> (32 - 8 * width);
+    let payload =
+        (word & 0x3F) | (word >> 2 & 0xFC0) | (word >> 4 & 0x3_F000) | (word >> 6 & 0x1C_0000);
+    // all ones for ASCII, which does not have 6 bit payloads
+    let ascii = lt_mask(lead, 0x80);
+    (lead & ascii) | (payload & !ascii)
+}
+
+/// Symbols decoded at every byte of the input, with masks of the bytes which start a char and how
+/// far their symbols move to the front, which are wiped when dropped with the `zeroize` feature
+struct Symbols {
+    b17s: Vec<B17>,
+    keep: Vec<u32>,
+    shift: Vec<u32>,
+}
+
+impl Symbols {
+    fn new(len: usize) -> Self {
+        Symbols {
+            b17s: vec![B17(0); len],
+            keep: vec![0; len],
+            shift: vec![0; len],
+        }
+    }
+
+    /// Move the kept symbols to the front, in order, without indexing by them. Each pass moves
+    /// the symbols by one bit of their shift, from the lowest, which never collide as the shifts
+    /// only grow along the input, and reads and writes every slot whatever the masks.
+    fn compact(&mut self) {
+        let len = self.b17s.len();
+        let mut step = 1;
+        while step < len {
+            let bit = step.trailing_zeros();
+            // ascending, so the slot `step` ahead is read before it is written
+            for idx in 0..len {
+                let stays = self.keep[idx] & !((self.shift[idx] >> bit & 1).wrapping_neg());
+                let (value, keep, shift) = if idx + step < len {
+                    let ahead = idx + step;
+                    (self.b17s[ahead].0, self.keep[ahead], self.shift[ahead])
+                } else {
+                    (0, 0, 0)
+                };
+                let moves = keep & (shift >> bit & 1).wrapping_neg();
+                self.b17s[idx] = B17((self.b17s[idx].0 & stays) | (value & moves));
+                self.shift[idx] = (self.shift[idx] & stays) | (shift & moves);
+                self.keep[idx] = stays | moves;
+            }
+            step <<= 1;
+        }
+    }
+}
+
+impl Drop for Symbols {
+    fn drop(&mut self) {
+        #[cfg(feature = "zeroize")]
+        {
+            zeroize::Zeroize::zeroize(&mut self.b17s);
+            zeroize::Zeroize::zeroize(&mut self.keep);
+            zeroize::Zeroize::zeroize(&mut self.shift);
+        }
+    }
+}
+
+/// Compare without exiting early on the first difference
+fn ends_with(bytes: &[u8], suffix: &[u8]) -> bool {
+    bytes.len() >= suffix.len()
+        && bytes[bytes.len() - suffix.len()..]
+            .iter()
+            .zip(suffix)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Encode some bytes to a base131072 encoded string in constant time
+///
+/// The output is the same as that of [`encode`](crate::encode).
+pub fn encode<T: AsRef<[u8]>>(input: T) -> String {
+    let input = input.as_ref();
+    let symbols = (input.len() * 8).div_ceil(17) + 1;
+    let mut out = Vec::with_capacity(symbols * 4);
+    for b17 in b17s(input) {
+        push_utf8(&mut out, encode_value(b17.0));
+    }
+    // the padding only depends on the length of the input
+    match calc_padding(input.len()) {
+        Some(Padding::Pad1) => out.extend_from_slice(&PAD1_UTF8),
+        Some(Padding::Pad2) => out.extend_from_slice(&PAD2_UTF8),
+        None => {}
+    }
+    into_string(out)
+}
+
+/// Decode a base131072 encoded string in constant time
+///
+/// The output and errors are the same as those of [`decode`](crate::decode). Which char is
+/// invalid is only determined after the whole string was found to be invalid.
+pub fn decode<T: AsRef<str>>(input: T) -> Result<Vec<u8>, InvalidChar> {
+    let string = input.as_ref();
+    let bytes = string.as_bytes();
+    // the padding only depends on the length of the data
+    let (bytes, padding) = if ends_with(bytes, &PAD1_UTF8) {
+        (&bytes[..bytes.len() - 4], 1)
+    } else if ends_with(bytes, &PAD2_UTF8) {
+        (&bytes[..bytes.len() - 4], 2)
+    } else {
+        (bytes, 0)
+    };
+    // decode at every byte, keeping the symbols of the bytes which start a char
+    let mut symbols = Symbols::new(bytes.len());
+    let mut count = 0;
+    let mut invalid = 0;
+    for idx in 0..bytes.len() {
+        let window = core::array::from_fn(|offset| bytes.get(idx + offset).copied().unwrap_or(0));
+        // continuation bytes are 0x80 to 0xBF
+        let start = !(!lt_mask(window[0] as u32, 0x80) & lt_mask(window[0] as u32, 0xC0));
+        let (value, valid) = decode_value(utf8_code_point(window));
+        symbols.b17s[idx] = B17(value & start);
+        symbols.keep[idx] = start;
+        symbols.shift[idx] = (idx - count) as u32;
+        count += (start & 1) as usize;
+        invalid |= start & !valid;
+    }
+    if invalid != 0 {
+        return Err(crate::decode(string).expect_err("invalid char not found"));
+    }
+    symbols.compact();
+    let mut out = Vec::with_capacity((count * 17).div_ceil(8));
+    extend_from_b17s(&mut out, &symbols.b17s[..count]);
+    if padding > out.len() {
+        return Err(crate::decode(string).expect_err("invalid padding not found"));
+    }
+    out.truncate(out.len() - padding);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::test_support::corrupted;
+    use std::time::Instant;
+
+    #[test]
+    fn matches_lookup() {
+        for value in (0..1 << 17).step_by(7) {
+            let code_point = B17(value).encode() as u32;
+            assert_eq!(encode_value(value), code_point);
+            assert_eq!(decode_value(code_point), (value, u32::MAX));
+        }
+        for code_point in (0..0x11_0000).step_by(29) {
+            if let Some(ch) = char::from_u32(code_point) {
+                let (value, valid) = decode_value(code_point);
+                match B17::decode(ch) {
+                    Some(b17) => assert_eq!((value, valid), (b17.0, u32::MAX)),
+                    None => assert_eq!(valid, 0),
+                }
+                assert_eq!(utf8_code_point(utf8_window(ch)), code_point);
+            }
+        }
+        assert_eq!(PAD1_UTF8, utf8_window(char::from_u32(PAD1).unwrap()));
+        assert_eq!(PAD2_UTF8, utf8_window(char::from_u32(PAD2).unwrap()));
+    }
+
+    fn utf8_window(ch: char) -> [u8; 4] {
+        let mut window = [0xAA; 4];
+        ch.encode_utf8(&mut window);
+        window
+    }
+
+    #[test]
+    fn matches_variable_time() {
+        let mut state = 0x5851_F42D_4C95_7F2D;
+        for len in 0..100 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = crate::encode(&input);
+            assert_eq!(encode(&input), encoded);
+            assert_eq!(decode(&encoded).unwrap(), input);
+            for corrupted in corrupted(&mut state, &encoded) {
+                assert_eq!(decode(&corrupted), crate::decode(&corrupted));
+            }
+        }
+    }
+
+    #[test]
+    fn compact_keeps_order() {
+        let mut state = 0x6A09_E667_F3BC_C908;
+        for len in 0..200 {
+            let mut symbols = Symbols::new(len);
+            let mut kept = Vec::new();
+            for idx in 0..len {
+                let value = random(&mut state) as u32 & 0x1_FFFF;
+                let start = ((random(&mut state) & 1) as u32).wrapping_neg();
+                symbols.b17s[idx] = B17(value & start);
+                symbols.keep[idx] = start;
+                symbols.shift[idx] = (idx - kept.len()) as u32;
+                if start != 0 {
+                    kept.push(value);
+                }
+            }
+            symbols.compact();
+            let compacted = symbols.b17s[..kept.len()].iter().map(|b17| b17.0);
+            assert!(compacted.eq(kept.iter().copied()), "len {len}");
+        }
+    }
+
+    /// Welch's t statistic between the timings of a fixed and random inputs, in the spirit of
+    /// dudect. Timings above the 90th percentile are cropped, as they are mostly interrupts.
+    fn leakage<I>(
+        mut fixed_input: impl FnMut() -> I,
+        mut random_input: impl FnMut() -> I,
+        f: fn(I),
+    ) -> f64 {
+        let mut state = 0x2127_599B_F432_5C37;
+        let mut timings = [Vec::new(), Vec::new()];
+        for _ in 0..100_000 {
+            let class = (random(&mut state) & 1) as usize;
+            let input = if class == 0 {
+                fixed_input()
+            } else {
+                random_input()
+            };
+            let start = Instant::now();
+            f(input);
+            timings[class].push(start.elapsed().as_nanos() as f64);
+        }
+        let mut all = timings.concat();
+        all.sort_by(f64::total_cmp);
+        let crop = all[all.len() * 9 / 10];
+        let [fixed, random] = timings.map(|timings| {
+            let timings = timings
+                .into_iter()
+                .filter(|&t| t <= crop)
+                .collect::<Vec<_>>();
+            let n = timings.len() as f64;
+            let mean = timings.iter().sum::<f64>() / n;
+            let var = timings.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            (n, mean, var)
+        });
+        (fixed.1 - random.1) / (fixed.2 / fixed.0 + random.2 / random.0).sqrt()
+    }
+
+    /// A dudect style test, which is too slow and noisy to run by default. Run it on a quiet
+    /// machine with `cargo test --release -- --ignored constant_time`.
+    #[test]
+    #[ignore]
+    fn constant_time() {
+        let mut state = 0x1405_7B7E_F767_814F;
+        let mut random_bytes = || core::array::from_fn::<u8, 34, _>(|_| random(&mut state) as u8);
+        let t = leakage(
+            || [0; 34],
+            &mut random_bytes,
+            |input| {
+                core::hint::black_box(encode(core::hint::black_box(input)));
+            },
+        );
+        assert!(t.abs() < 10.0, "encode leaks timing, t = {t:.2}");
+
+        // symbols of the same UTF-8 width, as the length of the input is not secret
+        let mut state = 0x94D0_49BB_1331_11EB;
+        let mut random_symbols = || {
+            (0..16)
+                .map(|_| B17(0xD800 + random(&mut state) as u32 % 0x1_0000).encode())
+                .collect::<String>()
+        };
+        let fixed = (0..16).map(|_| B17(0x1FFFF).encode()).collect::<String>();
+        let t = leakage(
+            || fixed.clone(),
+            &mut random_symbols,
+            |input| {
+                core::hint::black_box(decode(core::hint::black_box(input)).ok());
+            },
+        );
+        assert!(t.abs() < 10.0, "decode leaks timing, t = {t:.2}");
+
+        // a shuffle of 2 and 4 byte symbols against 3 byte ones, with the same byte and char counts
+        let mut state = 0xBB67_AE85_84CA_A73B;
+        let symbol = |state: &mut u64, width| loop {
+            let ch = B17(random(state) as u32 & 0x1_FFFF).encode();
+            if ch.len_utf8() == width {
+                break ch;
+            }
+        };
+        let fixed = (0..16).map(|_| symbol(&mut state, 3)).collect::<String>();
+        let mut random_widths = || {
+            let mut widths = [2, 4].repeat(8);
+            for idx in (1..widths.len()).rev() {
+                widths.swap(idx, random(&mut state) as usize % (idx + 1));
+            }
+            widths
+                .into_iter()
+                .map(|width| symbol(&mut state, width))
+                .collect::<String>()
+        };
+        let t = leakage(
+            || fixed.clone(),
+            &mut random_widths,
+            |input| {
+                core::hint::black_box(decode(core::hint::black_box(input)).ok());
+            },
+        );
+        assert!(
+            t.abs() < 10.0,
+            "decode leaks the widths of the chars, t = {t:.2}"
+        );
+    }
+}
@@ -0,0 +1,390 @@
+//! Adapters which encode what is written to a writer and decode what is read from a reader, with
+//! the state machines shared by the blocking and the async adapters
+
+use std::io::{self, Read, Write};
+
+use crate::stream::{Encoder, Utf8Decoder};
+use crate::DecodeError;
+
+#[cfg(feature = "tokio")]
+pub(crate) mod tokio;
+
+/// The most input bytes encoded at once, and the most UTF-8 read from the inner reader at once
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// The encoding which has not been written to the inner writer yet
+struct WriteState {
+    encoder: Encoder,
+    /// The encoded bytes not written yet are `out[written..]`
+    out: Vec<u8>,
+    written: usize,
+}
+
+impl WriteState {
+    fn new() -> Self {
+        Self {
+            encoder: Encoder::new(),
+            out: Vec::new(),
+            written: 0,
+        }
+    }
+
+    fn pending(&self) -> &[u8] {
+        &self.out[self.written..]
+    }
+
+    /// Record that `len` more bytes were written, failing like `write_all` if none were
+    fn advance(&mut self, len: usize) -> io::Result<()> {
+        if len == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        self.written += len;
+        Ok(())
+    }
+
+    /// Encode the start of `input` once everything encoded before is written, returning the
+    /// number of bytes taken
+    fn encode(&mut self, input: &[u8]) -> usize {
+        debug_assert!(self.pending().is_empty());
+        self.clear();
+        let len = input.len().min(CHUNK_SIZE);
+        self.encoder.update(&input[..len], &mut self.out);
+        len
+    }
+
+    /// Encode the held back bytes and the padding
+    fn finish(&mut self) {
+        self.encoder.finish(&mut self.out);
+    }
+
+    fn clear(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.out);
+        self.out.clear();
+        self.written = 0;
+    }
+}
+
+impl Drop for WriteState {
+    fn drop(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.out);
+    }
+}
+
+/// The decoding which has not been read yet
+struct ReadState {
+    decoder: Utf8Decoder,
+    input: Box<[u8]>,
+    /// The decoded bytes not read yet are `decoded[pos..]`
+    decoded: Vec<u8>,
+    pos: usize,
+    /// Set once the end of the input is decoded, or it failed to decode
+    done: Option<Result<(), DecodeError>>,
+}
+
+impl ReadState {
+    fn new() -> Self {
+        Self {
+            decoder: Utf8Decoder::new(),
+            input: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            decoded: Vec::new(),
+            pos: 0,
+            done: None,
+        }
+    }
+
+    /// Copy decoded bytes into `out`, or return the outcome at the end of the input, or `None`
+    /// if more input has to be decoded first
+    fn read(&mut self, out: &mut [u8]) -> Option<io::Result<usize>> {
+        if self.pos < self.decoded.len() {
+            let len = out.len().min(self.decoded.len() - self.pos);
+            out[..len].copy_from_slice(&self.decoded[self.pos..self.pos + len]);
+            self.pos += len;
+            return Some(Ok(len));
+        }
+        match self.done {
+            Some(Ok(())) => Some(Ok(0)),
+            Some(Err(err)) => Some(Err(io::Error::new(io::ErrorKind::InvalidData, err))),
+            None => None,
+        }
+    }
+
+    /// Decode the first `len` bytes read into `input`, where 0 marks the end of the input
+    fn decode(&mut self, len: usize) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.decoded);
+        self.decoded.clear();
+        self.pos = 0;
+        let decoded = &mut self.decoded;
+        let res = if len == 0 {
+            let res = self
+                .decoder
+                .finish(|bytes| decoded.extend_from_slice(bytes));
+            Some(res)
+        } else {
+            let res = self
+                .decoder
+                .update(&self.input[..len], |bytes| decoded.extend_from_slice(bytes));
+            res.err().map(Err)
+        };
+        // the bytes decoded before an error are still read before it
+        self.done = res;
+    }
+}
+
+impl Drop for ReadState {
+    fn drop(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.decoded);
+    }
+}
+
+/// A writer which encodes the bytes written to it and writes the UTF-8 of the encoding to an inner
+/// writer
+///
+/// The bytes of an incomplete block are held back, so [`finish`](Self::finish) has to be called
+/// to write them and the padding.
+///
+/// ```
+/// use std::io::Write;
+///
+/// let mut writer = base131072::EncoderWriter::new(Vec::new());
+/// writer.write_all(b"hello ").unwrap();
+/// writer.write_all(b"world").unwrap();
+/// let encoded = writer.finish().unwrap();
+/// assert_eq!(encoded, base131072::encode(b"hello world").as_bytes());
+/// ```
+pub struct EncoderWriter<W> {
+    inner: W,
+    state: WriteState,
+}
+
+impl<W: Write> EncoderWriter<W> {
+    /// Encode to `inner`
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            state: WriteState::new(),
+        }
+    }
+
+    /// The inner writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// The inner writer, which should not be written to directly
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Write the held back bytes and the padding, flush the inner writer and return it
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_pending()?;
+        self.state.finish();
+        self.write_pending()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        while !self.state.pending().is_empty() {
+            match self.inner.write(self.state.pending()) {
+                Ok(len) => self.state.advance(len)?,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncoderWriter<W> {
+    fn write(&mut self, input: &[u8]) -> io::Result<usize> {
+        self.write_pending()?;
+        Ok(self.state.encode(input))
+    }
+
+    /// Write everything encoded so far, which does not include the bytes of an incomplete block
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        self.inner.flush()
+    }
+}
+
+impl<W> std::fmt::Debug for EncoderWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncoderWriter").finish_non_exhaustive()
+    }
+}
+
+/// A reader which decodes the base131072 encoded UTF-8 read from an inner reader
+///
+/// Reading fails with an [`io::ErrorKind::InvalidData`] error wrapping the same [`DecodeError`]
+/// as [`decode_utf8_bytes`](crate::decode_utf8_bytes) if the input is invalid, after the bytes
+/// decoded before it are read.
+///
+/// ```
+/// use std::io::Read;
+///
+/// let encoded = base131072::encode(b"hello world");
+/// let mut reader = base131072::DecoderReader::new(encoded.as_bytes());
+/// let mut decoded = Vec::new();
+/// reader.read_to_end(&mut decoded).unwrap();
+/// assert_eq!(decoded, b"hello world");
+/// ```
+pub struct DecoderReader<R> {
+    inner: R,
+    state: ReadState,
+}
+
+impl<R: Read> DecoderReader<R> {
+    /// Decode from `inner`
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: ReadState::new(),
+        }
+    }
+
+    /// The inner reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// The inner reader, which should not be read from directly
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner reader, dropping the input which is read but not decoded yet
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for DecoderReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(res) = self.state.read(out) {
+                return res;
+            }
+            let len = self.inner.read(&mut self.state.input)?;
+            self.state.decode(len);
+        }
+    }
+}
+
+impl<R> std::fmt::Debug for DecoderReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecoderReader").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::test_support::corrupted_bytes;
+    use crate::{decode_utf8_bytes, encode};
+
+    /// Reads and writes at most a few bytes at a time
+    struct Trickle {
+        bytes: Vec<u8>,
+        pos: usize,
+        state: u64,
+    }
+
+    impl Trickle {
+        fn new(bytes: Vec<u8>, state: u64) -> Self {
+            Self {
+                bytes,
+                pos: 0,
+                state,
+            }
+        }
+
+        fn len(&mut self, max: usize) -> usize {
+            (random(&mut self.state) as usize % 7 + 1).min(max)
+        }
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let len = self.len(out.len()).min(self.bytes.len() - self.pos);
+            out[..len].copy_from_slice(&self.bytes[self.pos..self.pos + len]);
+            self.pos += len;
+            Ok(len)
+        }
+    }
+
+    impl Write for Trickle {
+        fn write(&mut self, input: &[u8]) -> io::Result<usize> {
+            let len = self.len(input.len());
+            self.bytes.extend_from_slice(&input[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn decode_error(err: io::Error) -> DecodeError {
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        *err.into_inner().unwrap().downcast::<DecodeError>().unwrap()
+    }
+
+    #[test]
+    fn encoding() {
+        let mut state = 0xA409_3822_299F_31D0;
+        for len in 0..100 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let mut writer = EncoderWriter::new(Trickle::new(Vec::new(), state));
+            let mut rest = &input[..];
+            while !rest.is_empty() {
+                let len = random(&mut state) as usize % 40 % (rest.len() + 1);
+                writer.write_all(&rest[..len]).unwrap();
+                rest = &rest[len..];
+            }
+            writer.flush().unwrap();
+            assert_eq!(writer.finish().unwrap().bytes, encode(&input).as_bytes());
+        }
+    }
+
+    #[test]
+    fn decoding() {
+        let mut state = 0x082E_FA98_EC4E_6C89;
+        for len in 0..100 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = encode(&input).into_bytes();
+            let mut decoded = Vec::new();
+            DecoderReader::new(Trickle::new(encoded.clone(), state))
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, input);
+
+            for corrupted in corrupted_bytes(&mut state, &encoded) {
+                let mut reader = DecoderReader::new(Trickle::new(corrupted.clone(), state));
+                let mut decoded = Vec::new();
+                match decode_utf8_bytes(&corrupted) {
+                    Ok(bytes) => {
+                        reader.read_to_end(&mut decoded).unwrap();
+                        assert_eq!(decoded, bytes);
+                    }
+                    Err(err) => {
+                        let res = reader.read_to_end(&mut decoded);
+                        assert_eq!(decode_error(res.unwrap_err()), err);
+                        // the error is sticky
+                        assert_eq!(decode_error(reader.read(&mut [0]).unwrap_err()), err);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! The async adapters, for the `AsyncWrite` and `AsyncRead` of `tokio`
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use ::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use pin_project_lite::pin_project;
+
+use super::{ReadState, WriteState};
+
+pin_project! {
+    /// An async writer which encodes the bytes written to it and writes the UTF-8 of the encoding
+    /// to an inner writer, like [`EncoderWriter`](crate::EncoderWriter)
+    ///
+    /// The bytes of an incomplete block are held back, so it has to be shut down to write them
+    /// and the padding. Writing after that fails.
+    ///
+    /// ```
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let mut writer = base131072::AsyncEncoderWriter::new(Vec::new());
+    /// writer.write_all(b"hello world").await.unwrap();
+    /// writer.shutdown().await.unwrap();
+    /// assert_eq!(writer.into_inner(), base131072::encode(b"hello world").as_bytes());
+    /// # });
+    /// ```
+    pub struct AsyncEncoderWriter<W> {
+        #[pin]
+        inner: W,
+        state: WriteState,
+        finished: bool,
+    }
+}
+
+impl<W: AsyncWrite> AsyncEncoderWriter<W> {
+    /// Encode to `inner`
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            state: WriteState::new(),
+            finished: false,
+        }
+    }
+
+    /// The inner writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// The inner writer, which should not be written to directly
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// The pinned inner writer, which should not be written to directly
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut W> {
+        self.project().inner
+    }
+
+    /// Unwrap the inner writer, dropping anything which is not written yet unless it has been shut
+    /// down
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn poll_pending(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        while !this.state.pending().is_empty() {
+            let len = ready!(this.inner.as_mut().poll_write(cx, this.state.pending()))?;
+            this.state.advance(len)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for AsyncEncoderWriter<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        input: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.finished {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "write after shutdown",
+            )));
+        }
+        ready!(self.as_mut().poll_pending(cx))?;
+        Poll::Ready(Ok(self.project().state.encode(input)))
+    }
+
+    /// Write everything encoded so far, which does not include the bytes of an incomplete block
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_pending(cx))?;
+        self.project().inner.poll_flush(cx)
+    }
+
+    /// Write the held back bytes and the padding, then shut the inner writer down
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_pending(cx))?;
+        let this = self.as_mut().project();
+        if !*this.finished {
+            this.state.finish();
+            *this.finished = true;
+        }
+        ready!(self.as_mut().poll_pending(cx))?;
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+impl<W> std::fmt::Debug for AsyncEncoderWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncEncoderWriter").finish_non_exhaustive()
+    }
+}
+
+pin_project! {
+    /// An async reader which decodes the base131072 encoded UTF-8 read from an inner reader, like
+    /// [`DecoderReader`](crate::DecoderReader)
+    ///
+    /// ```
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let encoded = base131072::encode(b"hello world");
+    /// let mut reader = base131072::AsyncDecoderReader::new(encoded.as_bytes());
+    /// let mut decoded = Vec::new();
+    /// reader.read_to_end(&mut decoded).await.unwrap();
+    /// assert_eq!(decoded, b"hello world");
+    /// # });
+    /// ```
+    pub struct AsyncDecoderReader<R> {
+        #[pin]
+        inner: R,
+        state: ReadState,
+    }
+}
+
+impl<R: AsyncRead> AsyncDecoderReader<R> {
+    /// Decode from `inner`
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: ReadState::new(),
+        }
+    }
+
+    /// The inner reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// The inner reader, which should not be read from directly
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// The pinned inner reader, which should not be read from directly
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().inner
+    }
+
+    /// Unwrap the inner reader, dropping the input which is read but not decoded yet
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for AsyncDecoderReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        loop {
+            if let Some(res) = this.state.read(out.initialize_unfilled()) {
+                return Poll::Ready(res.map(|len| out.advance(len)));
+            }
+            let mut input = ReadBuf::new(&mut this.state.input);
+            ready!(this.inner.as_mut().poll_read(cx, &mut input))?;
+            let len = input.filled().len();
+            this.state.decode(len);
+        }
+    }
+}
+
+impl<R> std::fmt::Debug for AsyncDecoderReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncDecoderReader").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::io::tests::decode_error;
+    use crate::{decode_utf8_bytes, encode};
+    use ::tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    #[::tokio::test]
+    async fn round_trip() {
+        let mut state = 0x4528_21E6_38D0_1377;
+        for len in 0..100 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            // a small buffer makes the writes partial
+            let (writer, reader) = duplex(5);
+            let mut writer = AsyncEncoderWriter::new(writer);
+            let mut reader = AsyncDecoderReader::new(reader);
+            let write = async {
+                let mut rest = &input[..];
+                while !rest.is_empty() {
+                    let len = random(&mut state) as usize % 40 % (rest.len() + 1);
+                    writer.write_all(&rest[..len]).await.unwrap();
+                    rest = &rest[len..];
+                }
+                writer.shutdown().await.unwrap();
+            };
+            let mut decoded = Vec::new();
+            let read = reader.read_to_end(&mut decoded);
+            let ((), res) = ::tokio::join!(write, read);
+            res.unwrap();
+            assert_eq!(decoded, input);
+            assert!(writer.write(b"more").await.is_err());
+        }
+    }
+
+    #[::tokio::test]
+    async fn shutdown_writes_padding() {
+        let (writer, mut reader) = duplex(3);
+        let mut writer = AsyncEncoderWriter::new(writer);
+        let write = async {
+            writer.write_all(b"hello world").await.unwrap();
+            writer.shutdown().await.unwrap();
+        };
+        let mut encoded = String::new();
+        let ((), res) = ::tokio::join!(write, reader.read_to_string(&mut encoded));
+        res.unwrap();
+        assert_eq!(encoded, encode(b"hello world"));
+    }
+
+    #[::tokio::test]
+    async fn invalid_input() {
+        for corrupted in [&b"\xF0\x9F"[..], b"\n", b"\xFF"] {
+            let (mut writer, reader) = duplex(2);
+            let mut reader = AsyncDecoderReader::new(reader);
+            let write = async {
+                writer.write_all(corrupted).await.unwrap();
+                writer.shutdown().await.unwrap();
+            };
+            let mut decoded = Vec::new();
+            let ((), res) = ::tokio::join!(write, reader.read_to_end(&mut decoded));
+            assert_eq!(
+                decode_error(res.unwrap_err()),
+                decode_utf8_bytes(corrupted).unwrap_err()
+            );
+        }
+    }
+}
@@ -9,6 +9,7 @@
 //! reason!
 
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
 
 #[cfg(not(feature = "std"))]
 extern crate alloc;
@@ -16,132 +17,268 @@ extern crate alloc;
 #[cfg(not(feature = "std"))]
 use alloc::{string::String, vec::Vec};
 
+mod alphabet;
+mod b131072_str;
+mod base_n;
+mod block;
+#[cfg(any(feature = "heapless", feature = "arrayvec"))]
+mod bounded;
+#[cfg(feature = "bytes")]
+mod buf;
+mod const_fn;
+pub mod ct;
+#[cfg(feature = "std")]
+mod io;
 mod lookup_table;
+mod macros;
+#[cfg(feature = "rayon")]
+mod par;
+pub mod qntm;
+mod radix;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(all(feature = "simd", not(feature = "forbid-unsafe")))]
+mod simd;
+mod stream;
+mod table_checks;
+#[cfg(test)]
+mod test_support;
+mod utf8;
+mod wrappers;
 
-use core::cmp::Ordering;
+use base_n::{padding_len, B8ToBnIter, BnToB8Iter};
+use block::{decode_block, encode_block, BLOCK_BYTES, BLOCK_SYMBOLS};
 use core::fmt;
-use lookup_table::{LOOKUP_TABLE, PAD1, PAD2};
+#[cfg(feature = "compact-tables")]
+use lookup_table::{CHECKPOINTS, CHECKPOINT_INTERVAL, COMPACT_RANGES};
+#[cfg(not(feature = "compact-tables"))]
+use lookup_table::{
+    DECODE_BLOCKS, DECODE_PAGE_BASES, DECODE_PAGE_BLOCKS, ENCODE_BLOCKS, ENCODE_PAGE_BASES,
+    ENCODE_PAGE_BLOCKS, INVALID, PAGE_BITS,
+};
+use lookup_table::{PAD1, PAD2};
+use utf8::Utf8Chars;
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, Zeroizing};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub use alphabet::Alphabet;
+pub use b131072_str::{B131072Str, B131072String, DecodedBytes, Symbols};
+pub use base_n::BaseN;
+#[cfg(feature = "arrayvec")]
+pub use bounded::{decode_arrayvec, encode_arrayvec};
+#[cfg(feature = "heapless")]
+pub use bounded::{decode_heapless, encode_heapless};
+#[cfg(feature = "bytes")]
+pub use buf::{decode_buf, encode_buf};
+pub use const_fn::{decode_array, encode_array, encoded_chars};
+#[cfg(feature = "tokio")]
+pub use io::tokio::{AsyncDecoderReader, AsyncEncoderWriter};
+#[cfg(feature = "std")]
+pub use io::{DecoderReader, EncoderWriter};
+#[cfg(feature = "rayon")]
+pub use par::{par_decode, par_encode};
+pub use radix::Radix;
+#[cfg(feature = "serde")]
+pub use serde::{from_str, to_string};
+pub use wrappers::{Base131072Array, Base131072Bytes};
+
+/// Items used by the macros and benchmarks, which are not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(not(feature = "std"))]
+    use alloc::{string::String, vec::Vec};
+
+    pub use crate::const_fn::{decode_str, decoded_len};
+    use crate::{utf8, Alphabet, InvalidChar, B17};
+
+    /// [`encode`](crate::encode) without vector instructions and with the binary search of the
+    /// alphabet's ranges instead of the page tables, the baseline of the benchmarks
+    pub fn binary_search_encode(input: &[u8]) -> String {
+        let mut out = Vec::with_capacity(crate::max_encoded_len(input.len()).unwrap());
+        crate::encode_utf8_with(input, 0, &mut out, |b17| {
+            Alphabet::BASE131072.encode(b17.0).unwrap() as u32
+        });
+        utf8::into_string(out)
+    }
+
+    /// [`decode`](crate::decode) without vector instructions and with the binary search of the
+    /// alphabet's ranges instead of the page tables, the baseline of the benchmarks
+    pub fn binary_search_decode(input: &str) -> Result<Vec<u8>, InvalidChar> {
+        let mut bytes = Vec::with_capacity(crate::min_decoded_len(input.len()));
+        crate::decode_chars_with(input.chars().map(Ok), &mut bytes, 0, |ch| {
+            Alphabet::BASE131072.decode(ch).map(B17)
+        })?;
+        Ok(bytes)
+    }
+}
+
+/// Base2048, packing 11 bits into each symbol
+pub const BASE2048: BaseN<11> = BaseN::new(Alphabet::BASE2048);
+/// Base32768, packing 15 bits into each symbol
+pub const BASE32768: BaseN<15> = BaseN::new(Alphabet::BASE32768);
+/// Base65536, packing 16 bits into each symbol
+pub const BASE65536: BaseN<16> = BaseN::new(Alphabet::BASE65536);
+/// Base131072, packing 17 bits into each symbol. Produces the same output as [`encode`].
+pub const BASE131072: BaseN<17> = BaseN::new(Alphabet::BASE131072);
+
+/// Mask of the bits of a symbol value or code point indexing into a page
+#[cfg(not(feature = "compact-tables"))]
+const PAGE_MASK: u32 = (1 << PAGE_BITS) - 1;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
 struct B17(u32);
 
+#[cfg(feature = "zeroize")]
+impl zeroize::DefaultIsZeroes for B17 {}
+
 impl B17 {
     fn encode(self) -> char {
-        match LOOKUP_TABLE.binary_search_by_key(&self.0, |&(idx, _, _)| idx) {
-            Ok(lookup_idx) => unsafe { char::from_u32_unchecked(LOOKUP_TABLE[lookup_idx].1) },
-            Err(lookup_idx) => {
-                let (idx, start, _) = LOOKUP_TABLE[lookup_idx - 1];
-                unsafe { char::from_u32_unchecked(self.0 - idx + start) }
-            }
-        }
+        table_char(self.code_point())
     }
 
-    fn decode(ch: char) -> Option<Self> {
-        let code_point = ch as u32;
-        let lookup_idx = LOOKUP_TABLE
-            .binary_search_by(|&(_, start, stop)| {
-                if start > code_point {
-                    Ordering::Greater
-                } else if code_point > stop {
-                    Ordering::Less
-                } else {
-                    Ordering::Equal
-                }
-            })
-            .ok()?;
-        let (idx, start, _) = LOOKUP_TABLE[lookup_idx];
-        Some(Self(code_point - start + idx))
+    /// The number of UTF-8 bytes of the symbol, without looking it up
+    #[cfg(any(feature = "zeroize", feature = "heapless", feature = "arrayvec"))]
+    fn utf8_width(self) -> usize {
+        1 + UTF8_WIDTH_VALUES
+            .iter()
+            .filter(|&&value| self.0 >= value)
+            .count()
     }
 }
 
-struct B8ToB17Iter<'a> {
-    data: &'a [u8],
-    index: usize,
-    bit_offset: usize,
+/// The char of a code point taken from the generated tables, which `table_checks` verifies to
+/// only contain valid chars at compile time
+#[inline(always)]
+fn table_char(code_point: u32) -> char {
+    #[cfg(not(feature = "forbid-unsafe"))]
+    {
+        // SAFETY: every code point in the generated tables is a valid char, which `table_checks`
+        // verifies at compile time
+        unsafe { char::from_u32_unchecked(code_point) }
+    }
+    #[cfg(feature = "forbid-unsafe")]
+    {
+        char::from_u32(code_point).expect("invalid char in the generated tables")
+    }
 }
 
-impl<'a> B8ToB17Iter<'a> {
-    fn new(data: &'a [u8]) -> Self {
-        Self {
-            data,
-            index: 0,
-            bit_offset: 0,
+#[cfg(not(feature = "compact-tables"))]
+impl B17 {
+    fn code_point(self) -> u32 {
+        let page = (self.0 >> PAGE_BITS) as usize;
+        let block = ENCODE_PAGE_BLOCKS[page] as usize;
+        let delta = ENCODE_BLOCKS[block][(self.0 & PAGE_MASK) as usize];
+        ENCODE_PAGE_BASES[page] + delta as u32
+    }
+
+    fn decode(ch: char) -> Option<Self> {
+        let code_point = ch as u32;
+        let page = (code_point >> PAGE_BITS) as usize;
+        let block = *DECODE_PAGE_BLOCKS.get(page)? as usize;
+        let delta = DECODE_BLOCKS[block][(code_point & PAGE_MASK) as usize];
+        if delta == INVALID {
+            None
+        } else {
+            Some(Self(DECODE_PAGE_BASES[page] + delta as u32))
         }
     }
 }
 
-impl<'a> Iterator for B8ToB17Iter<'a> {
-    type Item = B17;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.data.len() {
-            return None;
+/// Lookup through the delta encoded ranges, trading speed for a table small enough for flash
+/// constrained targets. Checkpoints narrow the search down to `CHECKPOINT_INTERVAL` ranges which
+/// are then walked linearly.
+#[cfg(feature = "compact-tables")]
+impl B17 {
+    fn code_point(self) -> u32 {
+        let checkpoint = CHECKPOINTS.partition_point(|&(offset, _)| offset <= self.0) - 1;
+        let (mut offset, mut start) = CHECKPOINTS[checkpoint];
+        let mut ranges = COMPACT_RANGES[checkpoint * CHECKPOINT_INTERVAL..].iter();
+        // the gap of the checkpoint's first range is already part of its start
+        let mut len = ranges.next().unwrap().1 as u32;
+        while self.0 - offset > len {
+            let &(gap, next_len) = ranges.next().unwrap();
+            offset += len + 1;
+            start += len + 1 + gap as u32;
+            len = next_len as u32;
         }
-        let mut res: u32 = 0;
-        res |= ((self.data[self.index] << self.bit_offset) as u32) << 9;
+        start + self.0 - offset
+    }
 
-        self.index += 1;
-        if self.index >= self.data.len() {
-            return Some(B17(res));
+    fn decode(ch: char) -> Option<Self> {
+        let code_point = ch as u32;
+        let checkpoint = CHECKPOINTS
+            .partition_point(|&(_, start)| start <= code_point)
+            .checked_sub(1)?;
+        let (mut offset, mut start) = CHECKPOINTS[checkpoint];
+        let mut ranges = COMPACT_RANGES[checkpoint * CHECKPOINT_INTERVAL..].iter();
+        let mut len = ranges.next().unwrap().1 as u32;
+        while code_point - start > len {
+            let &(gap, next_len) = ranges.next()?;
+            offset += len + 1;
+            start += len + 1 + gap as u32;
+            if code_point < start {
+                return None;
+            }
+            len = next_len as u32;
         }
-        res |= (self.data[self.index] as u32) << (1 + self.bit_offset);
+        Some(Self(offset + code_point - start))
+    }
+}
 
-        self.index += 1;
-        if self.index >= self.data.len() {
-            return Some(B17(res));
-        }
-        res |= (self.data[self.index] >> (7 - self.bit_offset)) as u32;
+struct B8ToB17Iter<'a>(B8ToBnIter<'a, 17>);
 
-        self.bit_offset += 1;
-        if self.bit_offset > 7 {
-            self.index += 1;
-            self.bit_offset = 0;
-        }
-        Some(B17(res))
+impl<'a> B8ToB17Iter<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self(B8ToBnIter::new(data))
     }
 }
 
-struct B17ToB8Iter<'a> {
-    data: &'a [B17],
-    index: usize,
-    bit_offset: usize,
+impl<'a> Iterator for B8ToB17Iter<'a> {
+    type Item = B17;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(B17)
+    }
 }
 
+type B17Values<'a> = core::iter::Map<core::slice::Iter<'a, B17>, fn(&B17) -> u32>;
+
+struct B17ToB8Iter<'a>(BnToB8Iter<B17Values<'a>, 17>);
+
 impl<'a> B17ToB8Iter<'a> {
     fn new(data: &'a [B17]) -> Self {
-        Self {
-            data,
-            index: 0,
-            bit_offset: 0,
-        }
+        Self(BnToB8Iter::new(data.iter().map(|b17| b17.0)))
     }
 }
 
 impl<'a> Iterator for B17ToB8Iter<'a> {
     type Item = u8;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.data.len() {
-            return None;
-        }
-        if self.bit_offset > 9 {
-            let mut next = (self.data[self.index].0 << (self.bit_offset - 9)) as u8;
-            self.index += 1;
-            if self.index >= self.data.len() {
-                if self.bit_offset == 17 {
-                    return None;
-                }
-                return Some(next);
-            }
-            self.bit_offset -= 9;
-            next |= (self.data[self.index].0 >> (17 - self.bit_offset)) as u8;
-            Some(next)
-        } else {
-            let next = (self.data[self.index].0 >> (9 - self.bit_offset)) as u8;
-            self.bit_offset += 8;
-            Some(next)
-        }
+        self.0.next()
     }
 }
 
+/// The symbols of some bytes, converting whole blocks at once and only the tail bit by bit
+fn b17s(input: &[u8]) -> impl Iterator<Item = B17> + '_ {
+    let blocks = input.chunks_exact(BLOCK_BYTES);
+    let tail = blocks.remainder();
+    blocks
+        .flat_map(|block| encode_block(block.try_into().unwrap()))
+        .map(B17)
+        .chain(B8ToB17Iter::new(tail))
+}
+
+/// Append the bytes of some symbols, converting whole blocks at once and only the tail bit by bit
+fn extend_from_b17s(bytes: &mut Vec<u8>, b17s: &[B17]) {
+    let blocks = b17s.chunks_exact(BLOCK_SYMBOLS);
+    let tail = blocks.remainder();
+    bytes.reserve((b17s.len() * 17).div_ceil(8));
+    for block in blocks {
+        let values = core::array::from_fn(|idx| block[idx].0);
+        bytes.extend_from_slice(&decode_block(&values));
+    }
+    bytes.extend(B17ToB8Iter::new(tail));
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum Padding {
     Pad1,
@@ -149,36 +286,185 @@ enum Padding {
 }
 
 fn calc_padding(byte_size: usize) -> Option<Padding> {
-    let bits = byte_size * 8;
-    if bits % 17 == 0 {
-        None
-    } else {
-        Some(match (16 - (bits % 17)) / 8 {
-            0 => Padding::Pad1,
-            1 => Padding::Pad2,
-            _ => unreachable!(),
-        })
+    match padding_len(17, byte_size) {
+        0 => None,
+        1 => Some(Padding::Pad1),
+        2 => Some(Padding::Pad2),
+        _ => unreachable!(),
     }
 }
 
 /// Encode some bytes to a base131072 encoded string
 pub fn encode<T: AsRef<[u8]>>(input: T) -> String {
+    let out = encode_to_utf8_vec(input);
+    utf8::into_string(out)
+}
+
+/// Encode some bytes to the UTF-8 bytes of a base131072 encoded string
+///
+/// This is what [`encode`] produces before wrapping it in a `String`, for callers which write the
+/// encoding out as bytes anyway.
+pub fn encode_to_utf8_vec<T: AsRef<[u8]>>(input: T) -> Vec<u8> {
+    let input = input.as_ref();
+    let mut out = Vec::with_capacity(max_encoded_len(input.len()).expect("capacity overflow"));
+    encode_utf8_into(input, &mut out);
+    out
+}
+
+/// Encode some bytes to a base131072 encoded string, returning an error instead of aborting if
+/// the memory for it cannot be allocated
+pub fn try_encode<T: AsRef<[u8]>>(input: T) -> Result<String, AllocError> {
     let input = input.as_ref();
-    let mut out = String::with_capacity(input.len() * 8 / 17);
-    for b17 in B8ToB17Iter::new(input) {
+    let mut out = Vec::new();
+    out.try_reserve_exact(max_encoded_len(input.len()).ok_or(AllocError)?)
+        .map_err(|_| AllocError)?;
+    encode_utf8_into(input, &mut out);
+    Ok(utf8::into_string(out))
+}
+
+/// An upper bound of the UTF-8 length of the encoding of `byte_size` bytes, as every symbol,
+/// including the padding, takes at most 4 bytes
+fn max_encoded_len(byte_size: usize) -> Option<usize> {
+    let symbols =
+        byte_size / BLOCK_BYTES * BLOCK_SYMBOLS + (byte_size % BLOCK_BYTES * 8).div_ceil(17);
+    (symbols + 1).checked_mul(4)
+}
+
+/// Append the UTF-8 encoding to `out`, which should have the capacity for `max_encoded_len` more
+/// bytes so it is not reallocated
+fn encode_utf8_into(input: &[u8], out: &mut Vec<u8>) {
+    let consumed = encode_prefix(input, out);
+    encode_utf8_with(input, consumed, out, B17::code_point);
+}
+
+/// Append the UTF-8 encoding of the input after the `consumed` bytes already encoded, looking up
+/// the code points with `code_point`
+#[inline(always)]
+fn encode_utf8_with(
+    input: &[u8],
+    consumed: usize,
+    out: &mut Vec<u8>,
+    code_point: impl Fn(B17) -> u32,
+) {
+    for b17 in b17s(&input[consumed..]) {
+        utf8::push_utf8(out, code_point(b17));
+    }
+    if let Some(padding) = calc_padding(input.len()) {
+        let padding = match padding {
+            Padding::Pad1 => PAD1,
+            Padding::Pad2 => PAD2,
+        };
+        utf8::push_utf8(out, padding);
+    }
+}
+
+/// The chars of the encoding of some bytes, including the padding
+#[cfg(any(feature = "heapless", feature = "arrayvec", feature = "bytes"))]
+fn encode_chars(input: &[u8]) -> impl Iterator<Item = char> + '_ {
+    let padding = calc_padding(input.len()).map(|padding| match padding {
+        Padding::Pad1 => table_char(PAD1),
+        Padding::Pad2 => table_char(PAD2),
+    });
+    b17s(input).map(B17::encode).chain(padding)
+}
+
+/// Encode some bytes to a base131072 encoded string which is wiped when dropped
+///
+/// The string is allocated with the exact encoded length up front, so no copies of the encoding
+/// are left behind by reallocations. Working out that length takes an extra pass over the input.
+#[cfg(feature = "zeroize")]
+pub fn encode_zeroizing<T: AsRef<[u8]>>(input: T) -> Zeroizing<String> {
+    let input = input.as_ref();
+    let mut out = Zeroizing::new(String::with_capacity(utf8_len(input)));
+    for b17 in b17s(input) {
         out.push(b17.encode());
     }
     if let Some(padding) = calc_padding(input.len()) {
         match padding {
-            Padding::Pad1 => out.push(unsafe { char::from_u32_unchecked(PAD1) }),
-            Padding::Pad2 => out.push(unsafe { char::from_u32_unchecked(PAD2) }),
+            Padding::Pad1 => out.push(table_char(PAD1)),
+            Padding::Pad2 => out.push(table_char(PAD2)),
         }
     }
     out
 }
 
-/// The error encountered when decoding an invalid Base2048 string
-#[derive(Debug, Clone, Copy)]
+/// The first symbol values which take 2, 3 and 4 bytes of UTF-8
+///
+/// The ranges of the alphabet are sorted by code point, so the UTF-8 width of a symbol only
+/// depends on how many of these values it reaches.
+#[cfg(any(feature = "zeroize", feature = "heapless", feature = "arrayvec"))]
+const UTF8_WIDTH_VALUES: [u32; 3] = [
+    const_fn::first_value_from(0x80),
+    const_fn::first_value_from(0x800),
+    const_fn::first_value_from(0x1_0000),
+];
+
+/// The number of UTF-8 bytes of the base131072 encoding of some bytes
+///
+/// This unpacks all the symbols a second time next to encoding them, though each one is only
+/// compared against the `UTF8_WIDTH_VALUES` rather than looked up.
+#[cfg(any(feature = "zeroize", feature = "heapless", feature = "arrayvec"))]
+fn utf8_len(input: &[u8]) -> usize {
+    let padding = match calc_padding(input.len()) {
+        // both padding characters are above U+FFFF
+        Some(_) => 4,
+        None => 0,
+    };
+    b17s(input).map(B17::utf8_width).sum::<usize>() + padding
+}
+
+/// Encode the leading whole blocks of the input with vector instructions if they are available,
+/// returning the number of bytes consumed
+#[inline]
+fn encode_prefix(input: &[u8], out: &mut Vec<u8>) -> usize {
+    #[cfg(all(feature = "simd", not(feature = "forbid-unsafe")))]
+    {
+        simd::encode_blocks(input, out)
+    }
+    #[cfg(not(all(feature = "simd", not(feature = "forbid-unsafe"))))]
+    {
+        let _ = (input, out);
+        0
+    }
+}
+
+/// Encode some bytes to base131072 encoded UTF-16 code units, without going through a `String`
+pub fn encode_utf16<T: AsRef<[u8]>>(input: T) -> Vec<u16> {
+    let input = input.as_ref();
+    let mut out = Vec::with_capacity(input.len() * 8 / 17 * 2);
+    let mut buf = [0; 2];
+    for b17 in b17s(input) {
+        out.extend_from_slice(b17.encode().encode_utf16(&mut buf));
+    }
+    if let Some(padding) = calc_padding(input.len()) {
+        let padding = match padding {
+            Padding::Pad1 => table_char(PAD1),
+            Padding::Pad2 => table_char(PAD2),
+        };
+        out.extend_from_slice(padding.encode_utf16(&mut buf));
+    }
+    out
+}
+
+/// The number of UTF-16 code units of the base131072 encoding of some bytes
+///
+/// Symbols above U+FFFF take two code units, so this can be used to compare the cost of this
+/// encoding against one restricted to the Basic Multilingual Plane.
+pub fn utf16_len<T: AsRef<[u8]>>(input: T) -> usize {
+    let input = input.as_ref();
+    let padding = match calc_padding(input.len()) {
+        // both padding characters are above U+FFFF
+        Some(_) => 2,
+        None => 0,
+    };
+    b17s(input)
+        .map(|b17| b17.encode().len_utf16())
+        .sum::<usize>()
+        + padding
+}
+
+/// The error encountered when decoding an invalid base131072 string
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct InvalidChar(
     /// The char index of the invalid character
     pub usize,
@@ -198,39 +484,452 @@ impl fmt::Display for InvalidChar {
 #[cfg(feature = "std")]
 impl std::error::Error for InvalidChar {}
 
+/// The error encountered when decoding can fail for more reasons than an invalid character
+///
+/// It is returned when decoding a `str` with limits or fallible allocation ([`try_decode`],
+/// [`DecodeLimits::try_decode`]) or into an output of a fixed size or capacity
+/// ([`decode_to_array`], [`decode_array`], [`Base131072Array`] and the `heapless` and `arrayvec`
+/// outputs), as well as when decoding UTF-8 bytes or UTF-16 code units ([`decode_utf8_bytes`],
+/// [`decode_utf16`]) and input streamed from `bytes` buffers or readers.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// A character which is not part of the encoding was encountered
+    InvalidChar(InvalidChar),
+    /// An unpaired UTF-16 surrogate was encountered
+    LoneSurrogate {
+        /// The code unit index of the surrogate
+        index: usize,
+        /// The surrogate code unit
+        unit: u16,
+    },
+    /// Bytes which are not valid UTF-8 were encountered
+    InvalidUtf8 {
+        /// The byte offset of the start of the invalid sequence
+        offset: usize,
+    },
+    /// The memory for the output could not be allocated
+    AllocError(AllocError),
+    /// The input has more chars than [`DecodeLimits::with_max_chars`] allows
+    TooManyChars {
+        /// The maximum number of chars
+        max: usize,
+    },
+    /// The output would have more bytes than [`DecodeLimits::with_max_output_bytes`] allows, or
+    /// than an output with a fixed capacity holds
+    OutputTooLarge {
+        /// The maximum number of bytes
+        max: usize,
+    },
+    /// The input decodes to a different number of bytes than the fixed size output holds
+    WrongLength {
+        /// The number of bytes of the output
+        expected: usize,
+        /// The number of bytes the input decodes to
+        actual: usize,
+    },
+}
+
+impl From<InvalidChar> for DecodeError {
+    fn from(err: InvalidChar) -> Self {
+        Self::InvalidChar(err)
+    }
+}
+
+impl From<AllocError> for DecodeError {
+    fn from(err: AllocError) -> Self {
+        Self::AllocError(err)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidChar(err) => err.fmt(f),
+            Self::LoneSurrogate { index, unit } => f.write_fmt(format_args!(
+                "unpaired surrogate {unit:#06x} encountered at code unit {index}"
+            )),
+            Self::InvalidUtf8 { offset } => f.write_fmt(format_args!(
+                "invalid UTF-8 encountered at byte offset {offset}"
+            )),
+            Self::AllocError(err) => err.fmt(f),
+            Self::TooManyChars { max } => {
+                f.write_fmt(format_args!("input exceeds the limit of {max} chars"))
+            }
+            Self::OutputTooLarge { max } => {
+                f.write_fmt(format_args!("output exceeds the limit of {max} bytes"))
+            }
+            Self::WrongLength { expected, actual } => f.write_fmt(format_args!(
+                "expected {expected} bytes but the input decodes to {actual}"
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidChar(err) => Some(err),
+            Self::AllocError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// The error encountered when the memory for an output could not be allocated
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
+
+/// The error encountered when an encoding does not fit into an output with a fixed capacity
+#[cfg(any(feature = "heapless", feature = "arrayvec"))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CapacityError {
+    /// The number of UTF-8 bytes of the encoding
+    pub required: usize,
+    /// The number of bytes the output holds
+    pub capacity: usize,
+}
+
+#[cfg(any(feature = "heapless", feature = "arrayvec"))]
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "the encoding takes {} bytes but the output only holds {}",
+            self.required, self.capacity
+        ))
+    }
+}
+
+#[cfg(all(feature = "std", any(feature = "heapless", feature = "arrayvec")))]
+impl std::error::Error for CapacityError {}
+
+/// Limits on untrusted input, which are checked before anything is allocated
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DecodeLimits {
+    max_chars: usize,
+    max_output_bytes: usize,
+}
+
+impl DecodeLimits {
+    /// No limits, other than the memory which can be allocated
+    pub const fn new() -> Self {
+        Self {
+            max_chars: usize::MAX,
+            max_output_bytes: usize::MAX,
+        }
+    }
+
+    /// Limit the number of chars of the input, including the padding
+    pub const fn with_max_chars(self, max_chars: usize) -> Self {
+        Self { max_chars, ..self }
+    }
+
+    /// Limit the number of bytes of the decoded output
+    pub const fn with_max_output_bytes(self, max_output_bytes: usize) -> Self {
+        Self {
+            max_output_bytes,
+            ..self
+        }
+    }
+
+    /// Decode a base131072 encoded string within these limits, returning an error instead of
+    /// aborting if the memory for the output cannot be allocated
+    ///
+    /// The output is allocated with its exact length up front.
+    pub fn try_decode<T: AsRef<str>>(&self, input: T) -> Result<Vec<u8>, DecodeError> {
+        let string = input.as_ref();
+        let chars = string
+            .chars()
+            .take(self.max_chars.saturating_add(1))
+            .count();
+        if chars > self.max_chars {
+            return Err(DecodeError::TooManyChars {
+                max: self.max_chars,
+            });
+        }
+        let (symbols, padding) = match string.chars().next_back().map(|ch| ch as u32) {
+            Some(PAD1) => (chars - 1, 1),
+            Some(PAD2) => (chars - 1, 2),
+            _ => (chars, 0),
+        };
+        let capacity = symbols_byte_len(symbols);
+        if capacity.saturating_sub(padding) > self.max_output_bytes {
+            return Err(DecodeError::OutputTooLarge {
+                max: self.max_output_bytes,
+            });
+        }
+        let mut bytes = Vec::new();
+        bytes.try_reserve_exact(capacity).map_err(|_| AllocError)?;
+        decode_str(string, 0, &mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The number of bytes `symbols` symbols decode to, before removing the padding
+const fn symbols_byte_len(symbols: usize) -> usize {
+    symbols / BLOCK_SYMBOLS * BLOCK_BYTES + (symbols % BLOCK_SYMBOLS * 17).div_ceil(8)
+}
+
+/// Decode a base131072 encoded string, returning an error instead of aborting if the memory for
+/// the output cannot be allocated
+///
+/// See [`DecodeLimits`] to also limit the size of untrusted input.
+pub fn try_decode<T: AsRef<str>>(input: T) -> Result<Vec<u8>, DecodeError> {
+    DecodeLimits::new().try_decode(input)
+}
+
 /// Decode a base131072 encoded string
+///
+/// With the `zeroize` feature the old buffer is wiped whenever the output grows, as by
+/// `decode_zeroizing`, so no copies of the decoded bytes are left behind.
 pub fn decode<T: AsRef<str>>(input: T) -> Result<Vec<u8>, InvalidChar> {
-    let mut string = input.as_ref();
-    if string.is_empty() {}
-    let padding = if let Some(ch) = string.chars().last() {
-        match ch as u32 {
-            PAD1 => 1,
-            PAD2 => 2,
-            _ => 0,
+    let string = input.as_ref();
+    let mut bytes = Vec::with_capacity(min_decoded_len(string.len()));
+    decode_str(string, 0, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decode a base131072 encoded string into a buffer which is wiped when dropped
+///
+/// The old buffer is wiped whenever the output grows, so no copies of the decoded bytes are left
+/// behind by reallocations. It is also wiped if decoding fails.
+#[cfg(feature = "zeroize")]
+pub fn decode_zeroizing<T: AsRef<str>>(input: T) -> Result<Zeroizing<Vec<u8>>, InvalidChar> {
+    let string = input.as_ref();
+    let mut bytes = Zeroizing::new(Vec::with_capacity(min_decoded_len(string.len())));
+    decode_str(string, 0, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decode a base131072 encoded string which decodes to exactly `N` bytes, without allocating
+///
+/// The number of chars is checked against the encoded length of `N` bytes before decoding, and
+/// fails with [`DecodeError::WrongLength`] if the string decodes to a different number of bytes.
+///
+/// ```
+/// use base131072::{decode_to_array, DecodeError};
+///
+/// let encoded = base131072::encode([7; 32]);
+/// assert_eq!(decode_to_array::<32>(&encoded), Ok([7; 32]));
+/// assert_eq!(
+///     decode_to_array::<16>(&encoded),
+///     Err(DecodeError::WrongLength { expected: 16, actual: 32 })
+/// );
+/// ```
+pub fn decode_to_array<const N: usize>(input: &str) -> Result<[u8; N], DecodeError> {
+    let symbols = (N * 8).div_ceil(17);
+    let expected_chars = symbols + calc_padding(N).is_some() as usize;
+    if input.chars().count() != expected_chars {
+        // strings which are not produced by `encode` may still decode to `N` bytes
+        let actual = const_fn::decoded_len(input)?;
+        if actual != N {
+            return Err(DecodeError::WrongLength {
+                expected: N,
+                actual,
+            });
         }
-    } else {
-        return Ok(Vec::new());
-    };
-    if padding > 0 {
-        let last_char_index = string.char_indices().last().unwrap().0;
-        string = &string[..last_char_index];
-    }
-    let mut b17s = Vec::with_capacity(string.len());
-    for (idx, ch) in string.chars().enumerate() {
-        if let Some(b17) = B17::decode(ch) {
-            b17s.push(b17);
-        } else {
-            return Err(InvalidChar(idx, ch));
+    }
+    let mut out = [0; N];
+    let mut len = 0;
+    let mut decoder = stream::Decoder::new();
+    for ch in input.chars() {
+        if let Some(block) = decoder.push(ch)? {
+            if let Some(out) = out.get_mut(len..len + BLOCK_BYTES) {
+                out.copy_from_slice(&block);
+            }
+            len += BLOCK_BYTES;
         }
     }
-    let mut bytes = B17ToB8Iter::new(&b17s).collect::<Vec<_>>();
-    bytes.truncate(bytes.len() - padding);
+    let (tail, tail_len) = decoder.finish()?;
+    if let Some(out) = out.get_mut(len..len + tail_len) {
+        out.copy_from_slice(&tail[..tail_len]);
+    }
+    len += tail_len;
+    // a wrong padding char changes the length without changing the number of chars
+    if len != N {
+        return Err(DecodeError::WrongLength {
+            expected: N,
+            actual: len,
+        });
+    }
+    Ok(out)
+}
+
+/// A lower bound of the decoded length of `utf8_len` bytes of UTF-8, as every char of at most 4
+/// bytes decodes to 17 bits
+fn min_decoded_len(utf8_len: usize) -> usize {
+    utf8_len * 17 / 32
+}
+
+/// Make room for `additional` more decoded bytes before appending them. With the `zeroize`
+/// feature the bytes are moved to a new buffer and the old one is wiped, instead of leaving them
+/// behind in memory freed by the reallocation.
+#[inline(always)]
+fn reserve_decoded(bytes: &mut Vec<u8>, additional: usize) {
+    #[cfg(feature = "zeroize")]
+    if bytes.capacity() - bytes.len() < additional {
+        let mut grown = Vec::with_capacity((bytes.len() + additional).max(bytes.capacity() * 2));
+        grown.extend_from_slice(bytes);
+        bytes.zeroize();
+        *bytes = grown;
+    }
+    #[cfg(not(feature = "zeroize"))]
+    let _ = (bytes, additional);
+}
+
+#[cfg(feature = "rayon")]
+fn is_pad(ch: char) -> bool {
+    matches!(ch as u32, PAD1 | PAD2)
+}
+
+/// Decode a string whose first char has the char index `first_char` in the whole input, appending
+/// to `bytes`
+fn decode_str(string: &str, first_char: usize, bytes: &mut Vec<u8>) -> Result<(), InvalidChar> {
+    let (consumed, skipped) = decode_prefix(string.as_bytes(), bytes);
+    let rest = &string[consumed..];
+    decode_chars(rest.chars().map(Ok), bytes, first_char + skipped)
+}
+
+/// Decode base131072 encoded UTF-16 code units, without going through a `String`
+pub fn decode_utf16<T: AsRef<[u16]>>(input: T) -> Result<Vec<u8>, DecodeError> {
+    let units = input.as_ref();
+    let mut index = 0;
+    let chars = char::decode_utf16(units.iter().copied()).map(|res| match res {
+        Ok(ch) => {
+            index += ch.len_utf16();
+            Ok(ch)
+        }
+        Err(err) => Err(DecodeError::LoneSurrogate {
+            index,
+            unit: err.unpaired_surrogate(),
+        }),
+    });
+    let mut bytes = Vec::new();
+    decode_chars(chars, &mut bytes, 0)?;
     Ok(bytes)
 }
 
+/// Decode base131072 encoded UTF-8 bytes, validating the UTF-8 while decoding
+///
+/// This saves a separate pass over the input compared to `std::str::from_utf8` followed by
+/// [`decode`].
+pub fn decode_utf8_bytes<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>, DecodeError> {
+    let input = input.as_ref();
+    let mut bytes = Vec::with_capacity(min_decoded_len(input.len()));
+    let (consumed, skipped) = decode_prefix(input, &mut bytes);
+    decode_chars(Utf8Chars::new(input, consumed), &mut bytes, skipped)?;
+    Ok(bytes)
+}
+
+/// Decode the leading whole blocks of valid UTF-8 encoded chars with vector instructions if they
+/// are available, appending to `bytes` and returning the number of bytes and chars consumed
+#[inline]
+fn decode_prefix(input: &[u8], bytes: &mut Vec<u8>) -> (usize, usize) {
+    #[cfg(all(feature = "simd", not(feature = "forbid-unsafe")))]
+    {
+        simd::decode_blocks(input, bytes)
+    }
+    #[cfg(not(all(feature = "simd", not(feature = "forbid-unsafe"))))]
+    {
+        let _ = (input, bytes);
+        (0, 0)
+    }
+}
+
+/// Wipe a buffer which may hold secrets before it is dropped, if the `zeroize` feature is enabled
+#[cfg(feature = "rayon")]
+fn wipe(buffer: &mut Vec<u8>) {
+    #[cfg(feature = "zeroize")]
+    buffer.zeroize();
+    #[cfg(not(feature = "zeroize"))]
+    let _ = buffer;
+}
+
+/// Decode a sequence of characters, of which only the last may be padding, appending to the bytes
+/// already decoded from the `skipped` chars before them
+///
+/// The symbols are converted a block at a time, so nothing is allocated other than the output.
+fn decode_chars<E: From<InvalidChar>>(
+    chars: impl Iterator<Item = Result<char, E>>,
+    bytes: &mut Vec<u8>,
+    skipped: usize,
+) -> Result<(), E> {
+    decode_chars_with(chars, bytes, skipped, B17::decode)
+}
+
+/// [`decode_chars`] looking up the symbols with `decode`
+#[inline(always)]
+fn decode_chars_with<E: From<InvalidChar>>(
+    chars: impl Iterator<Item = Result<char, E>>,
+    bytes: &mut Vec<u8>,
+    skipped: usize,
+    decode: impl Fn(char) -> Option<B17>,
+) -> Result<(), E> {
+    let mut block = Block([0; BLOCK_SYMBOLS]);
+    let mut filled = 0;
+    let mut padding = None;
+    for (idx, ch) in (skipped..).zip(chars) {
+        let ch = ch?;
+        if let Some((_, pad_idx, pad)) = padding {
+            return Err(InvalidChar(pad_idx, pad).into());
+        }
+        match ch as u32 {
+            PAD1 => padding = Some((1, idx, ch)),
+            PAD2 => padding = Some((2, idx, ch)),
+            _ => {
+                block.0[filled] = decode(ch).ok_or(InvalidChar(idx, ch))?.0;
+                filled += 1;
+                if filled == BLOCK_SYMBOLS {
+                    reserve_decoded(bytes, BLOCK_BYTES);
+                    bytes.extend_from_slice(&decode_block(&block.0));
+                    filled = 0;
+                }
+            }
+        }
+    }
+    reserve_decoded(bytes, symbols_byte_len(filled));
+    bytes.extend(BnToB8Iter::<_, 17>::new(block.0[..filled].iter().copied()));
+    if let Some((padding, pad_idx, pad)) = padding {
+        if padding > bytes.len() {
+            return Err(InvalidChar(pad_idx, pad).into());
+        }
+        bytes.truncate(bytes.len() - padding);
+    }
+    Ok(())
+}
+
+/// Symbol values being decoded, which are wiped when dropped with the `zeroize` feature
+struct Block([u32; BLOCK_SYMBOLS]);
+
+impl Drop for Block {
+    fn drop(&mut self) {
+        #[cfg(feature = "zeroize")]
+        self.0.zeroize();
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::unusual_byte_groupings)]
 mod tests {
     use super::*;
+    use crate::test_support::corrupted;
+    use block::tests::random;
 
     #[test]
     fn b17_encoding() {
@@ -257,6 +956,201 @@ mod tests {
         }
     }
 
+    #[test]
+    fn b17_lookup() {
+        let alphabet = Alphabet::BASE131072;
+        for value in 0..1 << 17 {
+            let ch = B17(value).encode();
+            assert_eq!(Some(ch), alphabet.encode(value));
+            assert_eq!(B17::decode(ch), Some(B17(value)));
+        }
+        for code_point in 0..=0x10FFFF {
+            if let Some(ch) = char::from_u32(code_point) {
+                assert_eq!(B17::decode(ch).map(|b17| b17.0), alphabet.decode(ch));
+            }
+        }
+    }
+
+    #[test]
+    fn binary_search_baseline() {
+        let mut state = 0x3C6E_F372_FE94_F82B;
+        for len in 0..100 {
+            let input = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = encode(&input);
+            assert_eq!(__private::binary_search_encode(&input), encoded);
+            assert_eq!(__private::binary_search_decode(&encoded).unwrap(), input);
+            for corrupted in corrupted(&mut state, &encoded) {
+                assert_eq!(
+                    __private::binary_search_decode(&corrupted),
+                    decode(&corrupted)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn checked_chars() {
+        for code_point in (0..1 << 17)
+            .map(|value| B17(value).code_point())
+            .chain([PAD1, PAD2])
+        {
+            assert_eq!(char::from_u32(code_point), Some(table_char(code_point)));
+        }
+    }
+
+    /// The table sizes given for the `compact-tables` feature in `Cargo.toml`
+    #[test]
+    fn table_sizes() {
+        use core::mem::size_of_val;
+        #[cfg(not(feature = "compact-tables"))]
+        {
+            let size = size_of_val(ENCODE_PAGE_BASES)
+                + size_of_val(ENCODE_PAGE_BLOCKS)
+                + size_of_val(ENCODE_BLOCKS)
+                + size_of_val(DECODE_PAGE_BASES)
+                + size_of_val(DECODE_PAGE_BLOCKS)
+                + size_of_val(DECODE_BLOCKS);
+            assert!(size < 144 * 1024, "page tables take {size} bytes");
+        }
+        #[cfg(feature = "compact-tables")]
+        {
+            let size = size_of_val(COMPACT_RANGES) + size_of_val(CHECKPOINTS);
+            assert!(size < 5 * 1024, "compact tables take {size} bytes");
+            assert!(size < size_of_val(lookup_table::LOOKUP_TABLE));
+        }
+    }
+
+    #[test]
+    fn fallible_allocation() {
+        let mut state = 0xE703_7ED1_A0B4_28DB;
+        for len in 0..300 {
+            let data = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = try_encode(&data).unwrap();
+            assert_eq!(encoded, encode(&data));
+            let decoded = try_decode(&encoded).unwrap();
+            assert_eq!(decoded, data);
+            assert_eq!(decoded.capacity(), len + padding_len(17, len));
+        }
+        for invalid in ["\n", "\u{2EA38}", "\u{2EA39}a"] {
+            assert_eq!(
+                try_decode(invalid),
+                Err(DecodeError::InvalidChar(decode(invalid).unwrap_err()))
+            );
+        }
+        assert_eq!(max_encoded_len(usize::MAX), None);
+    }
+
+    #[test]
+    fn decode_limits() {
+        let encoded = encode([0; 20]);
+        assert_eq!(encoded.chars().count(), 11);
+        let limits = DecodeLimits::new().with_max_chars(11);
+        assert_eq!(limits.try_decode(&encoded), Ok(vec![0; 20]));
+        assert_eq!(
+            limits.with_max_chars(10).try_decode(&encoded),
+            Err(DecodeError::TooManyChars { max: 10 })
+        );
+        let limits = DecodeLimits::new().with_max_output_bytes(20);
+        assert_eq!(limits.try_decode(&encoded), Ok(vec![0; 20]));
+        assert_eq!(
+            limits.with_max_output_bytes(19).try_decode(&encoded),
+            Err(DecodeError::OutputTooLarge { max: 19 })
+        );
+        // limits are checked before the chars are
+        assert_eq!(
+            DecodeLimits::new().with_max_chars(1).try_decode("\n\n"),
+            Err(DecodeError::TooManyChars { max: 1 })
+        );
+    }
+
+    #[test]
+    fn array_decoding() {
+        let mut state = 0xE703_7ED1_A0B4_28DB;
+        macro_rules! check_sizes {
+            ($($size:literal)*) => {$(
+                let input = core::array::from_fn::<u8, $size, _>(|_| random(&mut state) as u8);
+                let encoded = encode(input);
+                assert_eq!(decode_to_array::<$size>(&encoded), Ok(input));
+                assert_eq!(
+                    decode_to_array::<{ $size + 1 }>(&encoded),
+                    Err(DecodeError::WrongLength {
+                        expected: $size + 1,
+                        actual: $size
+                    })
+                );
+                for corrupted in corrupted(&mut state, &encoded) {
+                    let expected = match decode(&corrupted) {
+                        Ok(bytes) => bytes.try_into().map_err(|bytes: Vec<u8>| {
+                            DecodeError::WrongLength {
+                                expected: $size,
+                                actual: bytes.len(),
+                            }
+                        }),
+                        Err(err) => Err(err.into()),
+                    };
+                    assert_eq!(decode_to_array::<$size>(&corrupted), expected);
+                }
+            )*};
+        }
+        check_sizes!(0 1 2 3 4 7 8 15 16 17 18 32 33 34 35 64 100);
+        // the same number of chars with the other padding char
+        let pad1 = char::from_u32(PAD1).unwrap();
+        let swapped = encode([1; 3]).replace(char::from_u32(PAD2).unwrap(), &pad1.to_string());
+        assert_eq!(
+            decode_to_array::<3>(&swapped),
+            Err(DecodeError::WrongLength {
+                expected: 3,
+                actual: 4
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroizing() {
+        let mut state = 0xA076_1D64_78BD_642F;
+        for len in 0..300 {
+            let data = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let encoded = encode_zeroizing(&data);
+            assert_eq!(*encoded, encode(&data));
+            assert_eq!(encoded.capacity(), encoded.len());
+            assert_eq!(*decode_zeroizing(&*encoded).unwrap(), data);
+            let mut bytes = data.clone();
+            bytes.shrink_to_fit();
+            reserve_decoded(&mut bytes, BLOCK_BYTES);
+            assert_eq!(bytes, data);
+            assert!(bytes.capacity() >= len + BLOCK_BYTES);
+        }
+        for invalid in ["\n", "\u{2EA38}", "\u{2EA39}a"] {
+            assert_eq!(decode_zeroizing(invalid), Err(decode(invalid).unwrap_err()));
+        }
+    }
+
+    #[test]
+    fn block_conversion() {
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..1000 {
+            let len = random(&mut state) as usize % 200;
+            let data = (0..len)
+                .map(|_| random(&mut state) as u8)
+                .collect::<Vec<_>>();
+            let symbols = b17s(&data).collect::<Vec<_>>();
+            assert_eq!(symbols, B8ToB17Iter::new(&data).collect::<Vec<_>>());
+            let symbols = (0..len)
+                .map(|_| B17(random(&mut state) as u32 & 0x1FFFF))
+                .collect::<Vec<_>>();
+            let mut bytes = Vec::new();
+            extend_from_b17s(&mut bytes, &symbols);
+            assert_eq!(bytes, B17ToB8Iter::new(&symbols).collect::<Vec<_>>());
+        }
+    }
+
     #[test]
     fn b8_to_b17_iter() {
         assert_eq!(B8ToB17Iter::new(&[]).collect::<Vec<_>>(), vec![]);
@@ -534,10 +1428,10 @@ mod tests {
 
     #[test]
     fn encoding() {
-        assert_eq!(decode(encode(&[])).unwrap(), &[]);
-        assert_eq!(decode(encode(&[1])).unwrap(), &[1]);
-        assert_eq!(decode(encode(&[1, 2])).unwrap(), &[1, 2]);
-        assert_eq!(decode(encode(&[1, 2, 3])).unwrap(), &[1, 2, 3]);
+        assert_eq!(decode(encode([])).unwrap(), &[]);
+        assert_eq!(decode(encode([1])).unwrap(), &[1]);
+        assert_eq!(decode(encode([1, 2])).unwrap(), &[1, 2]);
+        assert_eq!(decode(encode([1, 2, 3])).unwrap(), &[1, 2, 3]);
         assert_eq!(
             decode(encode((0..17).collect::<Vec<_>>())).unwrap(),
             (0..17).collect::<Vec<_>>()
@@ -548,4 +1442,58 @@ mod tests {
         );
         assert_eq!(decode(encode(vec![100; 1024])).unwrap(), vec![100; 1024]);
     }
+
+    #[test]
+    fn invalid_padding() {
+        let pad = char::from_u32(PAD1).unwrap();
+        assert_eq!(decode(format!("{pad}")), Err(InvalidChar(0, pad)));
+        assert_eq!(decode(format!("{pad}a")), Err(InvalidChar(0, pad)));
+    }
+
+    #[test]
+    fn utf8_bytes_decoding() {
+        let data = (0..=255).collect::<Vec<u8>>();
+        let encoded = encode(&data);
+        assert_eq!(decode_utf8_bytes(encoded.as_bytes()).unwrap(), data);
+        let mut invalid = encoded.into_bytes();
+        let last = invalid.len() - 1;
+        invalid[last] = 0xFF;
+        assert_eq!(
+            decode_utf8_bytes(&invalid),
+            Err(DecodeError::InvalidUtf8 { offset: last - 3 })
+        );
+        assert_eq!(
+            decode_utf8_bytes(b"\t"),
+            Err(DecodeError::InvalidChar(InvalidChar(0, '\t')))
+        );
+    }
+
+    #[test]
+    fn utf16_encoding() {
+        for len in [0, 1, 2, 3, 17, 255, 1024] {
+            let data = (0..len).map(|n| (n * 7) as u8).collect::<Vec<_>>();
+            let utf16 = encode_utf16(&data);
+            assert_eq!(utf16, encode(&data).encode_utf16().collect::<Vec<_>>());
+            assert_eq!(utf16.len(), utf16_len(&data));
+            assert_eq!(decode_utf16(&utf16).unwrap(), data);
+        }
+        assert_eq!(
+            decode_utf16([0x0041, 0xD800, 0x0041]),
+            Err(DecodeError::LoneSurrogate {
+                index: 1,
+                unit: 0xD800
+            })
+        );
+        assert_eq!(
+            decode_utf16([0xD840, 0xDC00, 0xDC00]),
+            Err(DecodeError::LoneSurrogate {
+                index: 2,
+                unit: 0xDC00
+            })
+        );
+        assert_eq!(
+            decode_utf16([0x0009]),
+            Err(DecodeError::InvalidChar(InvalidChar(0, '\t')))
+        );
+    }
 }
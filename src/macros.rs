@@ -0,0 +1,68 @@
+//! Macros decoding base131072 literals at compile time
+
+/// Decode a base131072 string literal at compile time to a `&'static [u8; N]`
+///
+/// Strings which are not valid base131072 fail to compile. Some of the chars of the alphabet are
+/// changed by Unicode normalization, which some editors and terminals apply, so they may be
+/// safer to paste as `\u{...}` escapes.
+///
+/// ```
+/// const MAGIC: &[u8; 5] = base131072::base131072!("𡂗𣼰蓖𮨸");
+/// assert_eq!(MAGIC, b"\x93NUMP");
+/// ```
+///
+/// ```compile_fail
+/// let bytes = base131072::base131072!("not base131072\n");
+/// ```
+#[macro_export]
+macro_rules! base131072 {
+    ($string:expr $(,)?) => {{
+        const LEN: usize = match $crate::__private::decoded_len($string) {
+            ::core::result::Result::Ok(len) => len,
+            ::core::result::Result::Err(_) => ::core::panic!("invalid base131072 string"),
+        };
+        const BYTES: [u8; LEN] = match $crate::__private::decode_str::<LEN>($string) {
+            ::core::result::Result::Ok(bytes) => bytes,
+            ::core::result::Result::Err(_) => ::core::unreachable!(),
+        };
+        &BYTES
+    }};
+}
+
+/// Decode a file containing a base131072 string at compile time to a `&'static [u8; N]`
+///
+/// The path is resolved like with `include_str!`, relative to the file the macro is used in. The
+/// file must contain nothing but the encoded string, apart from trailing ASCII whitespace such as
+/// the final newline most editors add, which is ignored.
+///
+/// ```ignore
+/// static KEY: &[u8; 32] = base131072::include_base131072!("key.b131072");
+/// ```
+#[macro_export]
+macro_rules! include_base131072 {
+    ($path:expr $(,)?) => {
+        $crate::base131072!(::core::include_str!($path).trim_ascii_end())
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn literals() {
+        assert_eq!(crate::base131072!(""), &[]);
+        let bytes: &'static [u8; 11] =
+            crate::base131072!("\u{f917}\u{29bac}\u{262fe}\u{260f2}\u{5262}\u{1bc56}\u{2ea38}");
+        assert_eq!(bytes, b"hello world");
+        const CONST: &[u8] =
+            crate::base131072!("\u{f917}\u{29bac}\u{262fe}\u{260f2}\u{5262}\u{1bc56}\u{2ea38}",);
+        assert_eq!(CONST, bytes);
+    }
+
+    #[test]
+    fn include() {
+        let bytes = crate::include_base131072!("../testdata/hello.b131072");
+        assert_eq!(bytes, b"hello world");
+        let bytes = crate::include_base131072!("../testdata/hello_newline.b131072");
+        assert_eq!(bytes, b"hello world");
+    }
+}
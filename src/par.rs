@@ -0,0 +1,168 @@
+//! Encoding and decoding of large buffers split into chunks of whole blocks, which are converted
+//! independently on the rayon thread pool.
+
+use rayon::prelude::*;
+
+use crate::block::{BLOCK_BYTES, BLOCK_SYMBOLS};
+use crate::lookup_table::PAD1;
+use crate::utf8::into_string;
+use crate::{decode_str, encode_to_utf8_vec, is_pad, min_decoded_len, wipe, InvalidChar};
+
+/// The number of input bytes encoded by each task
+const ENCODE_CHUNK_BYTES: usize = BLOCK_BYTES << 12;
+/// The number of input bytes scanned for char boundaries by each task
+const DECODE_SEGMENT_BYTES: usize = 1 << 16;
+
+/// Encode some bytes to a base131072 encoded string, using all threads of the rayon thread pool
+///
+/// The output is the same as that of [`encode`](crate::encode).
+pub fn par_encode<T: AsRef<[u8]>>(input: T) -> String {
+    encode_chunks(input.as_ref(), ENCODE_CHUNK_BYTES)
+}
+
+/// Decode a base131072 encoded string, using all threads of the rayon thread pool
+///
+/// The output and errors are the same as those of [`decode`](crate::decode).
+pub fn par_decode<T: AsRef<str>>(input: T) -> Result<Vec<u8>, InvalidChar> {
+    decode_segments(input.as_ref(), DECODE_SEGMENT_BYTES)
+}
+
+fn encode_chunks(input: &[u8], chunk_bytes: usize) -> String {
+    debug_assert_eq!(chunk_bytes % BLOCK_BYTES, 0);
+    // chunks of whole blocks encode without padding, so only the last one can be padded
+    let chunks = input
+        .par_chunks(chunk_bytes)
+        .map(encode_to_utf8_vec)
+        .collect::<Vec<_>>();
+    let out = chunks.concat();
+    for mut chunk in chunks {
+        wipe(&mut chunk);
+    }
+    into_string(out)
+}
+
+fn is_char_start(byte: u8) -> bool {
+    // continuation bytes are 0x80 to 0xBF, the smallest signed bytes
+    byte as i8 >= -0x40
+}
+
+fn decode_segments(string: &str, segment_bytes: usize) -> Result<Vec<u8>, InvalidChar> {
+    let (body, pad) = match string.chars().next_back() {
+        Some(ch) if is_pad(ch) => (&string[..string.len() - ch.len_utf8()], Some(ch)),
+        _ => (string, None),
+    };
+    let bytes = body.as_bytes();
+    let counts = bytes
+        .par_chunks(segment_bytes)
+        .map(|segment| segment.iter().filter(|&&byte| is_char_start(byte)).count())
+        .collect::<Vec<_>>();
+    let mut first_chars = Vec::with_capacity(counts.len());
+    let mut char_count = 0;
+    for count in counts {
+        first_chars.push(char_count);
+        char_count += count;
+    }
+    // split every segment at its first char whose index is a multiple of the block size, so every
+    // chunk but the last decodes to whole blocks. Segments without such a char are not split.
+    let splits = first_chars
+        .par_iter()
+        .enumerate()
+        .filter_map(|(segment, &first_char)| {
+            let skip = (BLOCK_SYMBOLS - first_char % BLOCK_SYMBOLS) % BLOCK_SYMBOLS;
+            let start = segment * segment_bytes;
+            let (offset, _) = bytes[start..]
+                .iter()
+                .take(segment_bytes)
+                .enumerate()
+                .filter(|&(_, &byte)| is_char_start(byte))
+                .nth(skip)?;
+            Some((start + offset, first_char + skip))
+        })
+        .collect::<Vec<_>>();
+    let chunks = splits
+        .par_iter()
+        .enumerate()
+        .map(|(idx, &(start, first_char))| {
+            let (end, end_char) = splits
+                .get(idx + 1)
+                .copied()
+                .unwrap_or((body.len(), char_count));
+            let chunk = &body[start..end];
+            let mut decoded = Vec::with_capacity(min_decoded_len(chunk.len()));
+            decode_str(chunk, first_char, &mut decoded)?;
+            // padding is only valid as the last char of the whole string
+            match chunk.chars().next_back() {
+                Some(ch) if is_pad(ch) => Err(InvalidChar(end_char - 1, ch)),
+                _ => Ok(decoded),
+            }
+        })
+        .collect::<Vec<_>>();
+    // report the first error, like the sequential decoder would
+    let mut out = Vec::with_capacity(chunks.iter().flatten().map(Vec::len).sum());
+    for chunk in chunks {
+        let mut chunk = chunk?;
+        out.extend_from_slice(&chunk);
+        wipe(&mut chunk);
+    }
+    if let Some(pad) = pad {
+        let padding = if pad as u32 == PAD1 { 1 } else { 2 };
+        if padding > out.len() {
+            return Err(InvalidChar(char_count, pad));
+        }
+        out.truncate(out.len() - padding);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::test_support::corrupted;
+    use crate::{decode, encode};
+
+    fn random_bytes(state: &mut u64, len: usize) -> Vec<u8> {
+        (0..len).map(|_| random(state) as u8).collect()
+    }
+
+    #[test]
+    fn encoding_matches_sequential() {
+        let mut state = 0x2545_F491_4F6C_DD1D;
+        for len in (0..200).chain([ENCODE_CHUNK_BYTES * 3 + 5]) {
+            let input = random_bytes(&mut state, len);
+            let expected = encode(&input);
+            assert_eq!(par_encode(&input), expected);
+            for chunk_blocks in 1..4 {
+                assert_eq!(encode_chunks(&input, chunk_blocks * BLOCK_BYTES), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn decoding_matches_sequential() {
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+        for len in (0..200).chain([DECODE_SEGMENT_BYTES * 3 + 5]) {
+            let encoded = encode(random_bytes(&mut state, len));
+            let expected = decode(&encoded);
+            assert_eq!(par_decode(&encoded), expected);
+            for segment_bytes in [1, 5, 13, 32] {
+                assert_eq!(decode_segments(&encoded, segment_bytes), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn errors_match_sequential() {
+        let mut state = 0xD1B5_4A32_D192_ED03;
+        for _ in 0..500 {
+            let len = random(&mut state) as usize % 100;
+            let encoded = encode(random_bytes(&mut state, len));
+            for corrupted in corrupted(&mut state, &encoded) {
+                let expected = decode(&corrupted);
+                for segment_bytes in [1, 7, 32, DECODE_SEGMENT_BYTES] {
+                    assert_eq!(decode_segments(&corrupted, segment_bytes), expected);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,554 @@
+//! Encodings compatible with qntm's [Base65536](https://github.com/qntm/base65536),
+//! [Base32768](https://github.com/qntm/base32768) and [Base2048](https://github.com/qntm/base2048)
+//!
+//! These encodings do not use padding code points. Instead, the final bits are written with a
+//! smaller tail repertoire when they fit, and are otherwise filled up with `1` bits.
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::alphabet::Alphabet;
+use crate::InvalidChar;
+
+/// Base2048 as specified by qntm's `base2048` package
+pub const BASE2048: Qntm<11> = Qntm::new(
+    Alphabet::new(BASE2048_TABLE),
+    Alphabet::new(BASE2048_TAIL_TABLE),
+    false,
+);
+/// Base32768 as specified by qntm's `base32768` package
+pub const BASE32768: Qntm<15> = Qntm::new(
+    Alphabet::new(BASE32768_TABLE),
+    Alphabet::new(BASE32768_TAIL_TABLE),
+    false,
+);
+/// Base65536 as specified by qntm's `base65536` package
+pub const BASE65536: Qntm<16> = Qntm::new(
+    Alphabet::new(BASE65536_TABLE),
+    Alphabet::new(BASE65536_TAIL_TABLE),
+    true,
+);
+
+/// An encoding following qntm's scheme of packing `BITS` bits into every code point
+#[derive(Debug, Clone, Copy)]
+pub struct Qntm<const BITS: u32> {
+    repertoire: Alphabet,
+    tail: Alphabet,
+    byte_swapped: bool,
+}
+
+impl<const BITS: u32> Qntm<BITS> {
+    /// The number of bits encoded by a tail code point
+    const TAIL_BITS: u32 = BITS - 8;
+
+    /// Create an encoding from a repertoire of `2^BITS` code points and a tail repertoire of
+    /// `2^(BITS - 8)` code points. If `byte_swapped` is set, the bytes of every 16 bit symbol are
+    /// swapped before looking them up, like Base65536 does.
+    const fn new(repertoire: Alphabet, tail: Alphabet, byte_swapped: bool) -> Self {
+        assert!(repertoire.len() == 1 << BITS);
+        assert!(tail.len() == 1 << Self::TAIL_BITS);
+        assert!(!byte_swapped || BITS == 16);
+        Self {
+            repertoire,
+            tail,
+            byte_swapped,
+        }
+    }
+
+    fn order(&self, value: u32) -> u32 {
+        if self.byte_swapped {
+            (value as u16).swap_bytes() as u32
+        } else {
+            value
+        }
+    }
+
+    /// Encode some bytes to a string
+    pub fn encode<T: AsRef<[u8]>>(&self, input: T) -> String {
+        let input = input.as_ref();
+        let mut out = String::with_capacity(input.len() * 8 / BITS as usize + 1);
+        let mut acc: u32 = 0;
+        let mut acc_bits = 0;
+        for &byte in input {
+            acc = (acc << 8) | byte as u32;
+            acc_bits += 8;
+            if acc_bits >= BITS {
+                acc_bits -= BITS;
+                let value = self.order(acc >> acc_bits);
+                out.push(self.repertoire.encode(value).unwrap());
+                acc &= mask(acc_bits);
+            }
+        }
+        if acc_bits > 0 {
+            if acc_bits <= Self::TAIL_BITS {
+                let fill = Self::TAIL_BITS - acc_bits;
+                out.push(self.tail.encode((acc << fill) | mask(fill)).unwrap());
+            } else {
+                let fill = BITS - acc_bits;
+                let value = self.order((acc << fill) | mask(fill));
+                out.push(self.repertoire.encode(value).unwrap());
+            }
+        }
+        out
+    }
+
+    /// Decode an encoded string
+    ///
+    /// Fails on code points outside of the repertoires, on tail code points before the end of the
+    /// input and when the final fill bits are not all set.
+    pub fn decode<T: AsRef<str>>(&self, input: T) -> Result<Vec<u8>, InvalidChar> {
+        let input = input.as_ref();
+        let mut out = Vec::with_capacity(input.len());
+        let mut acc: u32 = 0;
+        let mut acc_bits = 0;
+        let mut last = None;
+        for (idx, (byte_idx, ch)) in input.char_indices().enumerate() {
+            if let Some(value) = self.repertoire.decode(ch) {
+                acc = (acc << BITS) | self.order(value);
+                acc_bits += BITS;
+            } else if let Some(value) = self.tail.decode(ch) {
+                if byte_idx + ch.len_utf8() != input.len() {
+                    return Err(InvalidChar(idx, ch));
+                }
+                acc = (acc << Self::TAIL_BITS) | value;
+                acc_bits += Self::TAIL_BITS;
+            } else {
+                return Err(InvalidChar(idx, ch));
+            }
+            while acc_bits >= 8 {
+                acc_bits -= 8;
+                out.push((acc >> acc_bits) as u8);
+                acc &= mask(acc_bits);
+            }
+            last = Some((idx, ch));
+        }
+        if acc != mask(acc_bits) {
+            let (idx, ch) = last.unwrap();
+            return Err(InvalidChar(idx, ch));
+        }
+        Ok(out)
+    }
+}
+
+const fn mask(bits: u32) -> u32 {
+    (1 << bits) - 1
+}
+
+/// The 2^11 code points of qntm's Base2048 repertoire
+const BASE2048_TABLE: &[(u32, u32, u32)] = &[
+    (0, 0x0038, 0x0039),
+    (2, 0x0041, 0x005A),
+    (28, 0x0061, 0x007A),
+    (54, 0x00C6, 0x00C6),
+    (55, 0x00D0, 0x00D0),
+    (56, 0x00D8, 0x00D8),
+    (57, 0x00DE, 0x00DF),
+    (59, 0x00E6, 0x00E6),
+    (60, 0x00F0, 0x00F0),
+    (61, 0x00F8, 0x00F8),
+    (62, 0x00FE, 0x00FE),
+    (63, 0x0110, 0x0111),
+    (65, 0x0126, 0x0127),
+    (67, 0x0131, 0x0131),
+    (68, 0x0138, 0x0138),
+    (69, 0x0141, 0x0142),
+    (71, 0x014A, 0x014B),
+    (73, 0x0152, 0x0153),
+    (75, 0x0166, 0x0167),
+    (77, 0x0180, 0x019F),
+    (109, 0x01A2, 0x01AE),
+    (122, 0x01B1, 0x01C3),
+    (141, 0x01DD, 0x01DD),
+    (142, 0x01E4, 0x01E5),
+    (144, 0x01F6, 0x01F7),
+    (146, 0x021C, 0x021D),
+    (148, 0x0220, 0x0225),
+    (154, 0x0234, 0x02AF),
+    (278, 0x0370, 0x0373),
+    (282, 0x0376, 0x0377),
+    (284, 0x037B, 0x037D),
+    (287, 0x037F, 0x037F),
+    (288, 0x0391, 0x03A1),
+    (305, 0x03A3, 0x03A9),
+    (312, 0x03B1, 0x03C9),
+    (337, 0x03CF, 0x03CF),
+    (338, 0x03D7, 0x03EF),
+    (363, 0x03F3, 0x03F3),
+    (364, 0x03F7, 0x03F8),
+    (366, 0x03FA, 0x03FF),
+    (372, 0x0402, 0x0402),
+    (373, 0x0404, 0x0406),
+    (376, 0x0408, 0x040B),
+    (380, 0x040F, 0x0418),
+    (390, 0x041A, 0x0438),
+    (421, 0x043A, 0x044F),
+    (443, 0x0452, 0x0452),
+    (444, 0x0454, 0x0456),
+    (447, 0x0458, 0x045B),
+    (451, 0x045F, 0x0475),
+    (474, 0x0478, 0x0481),
+    (484, 0x048A, 0x04C0),
+    (539, 0x04C3, 0x04CF),
+    (552, 0x04D4, 0x04D5),
+    (554, 0x04D8, 0x04D9),
+    (556, 0x04E0, 0x04E1),
+    (558, 0x04E8, 0x04E9),
+    (560, 0x04F6, 0x04F7),
+    (562, 0x04FA, 0x052F),
+    (616, 0x0531, 0x0556),
+    (654, 0x0561, 0x0586),
+    (692, 0x05D0, 0x05EA),
+    (719, 0x05F0, 0x05F2),
+    (722, 0x0620, 0x0621),
+    (724, 0x0627, 0x063F),
+    (749, 0x0641, 0x064A),
+    (759, 0x0660, 0x0669),
+    (769, 0x066E, 0x066F),
+    (771, 0x0671, 0x0674),
+    (775, 0x0679, 0x06BF),
+    (846, 0x06C1, 0x06C1),
+    (847, 0x06C3, 0x06D2),
+    (863, 0x06D5, 0x06D5),
+    (864, 0x06EE, 0x06FC),
+    (879, 0x06FF, 0x06FF),
+    (880, 0x0710, 0x0710),
+    (881, 0x0712, 0x072F),
+    (911, 0x074D, 0x07A5),
+    (1000, 0x07B1, 0x07B1),
+    (1001, 0x07C0, 0x07EA),
+    (1044, 0x0800, 0x0815),
+    (1066, 0x0840, 0x0858),
+    (1091, 0x0860, 0x086A),
+    (1102, 0x08A0, 0x08B4),
+    (1123, 0x08B6, 0x08BD),
+    (1131, 0x0904, 0x0928),
+    (1168, 0x092A, 0x0930),
+    (1175, 0x0932, 0x0933),
+    (1177, 0x0935, 0x0939),
+    (1182, 0x093D, 0x093D),
+    (1183, 0x0950, 0x0950),
+    (1184, 0x0960, 0x0961),
+    (1186, 0x0966, 0x096F),
+    (1196, 0x0972, 0x0980),
+    (1211, 0x0985, 0x098C),
+    (1219, 0x098F, 0x0990),
+    (1221, 0x0993, 0x09A8),
+    (1243, 0x09AA, 0x09B0),
+    (1250, 0x09B2, 0x09B2),
+    (1251, 0x09B6, 0x09B9),
+    (1255, 0x09BD, 0x09BD),
+    (1256, 0x09CE, 0x09CE),
+    (1257, 0x09E0, 0x09E1),
+    (1259, 0x09E6, 0x09F1),
+    (1271, 0x09F4, 0x09F9),
+    (1277, 0x09FC, 0x09FC),
+    (1278, 0x0A05, 0x0A0A),
+    (1284, 0x0A0F, 0x0A10),
+    (1286, 0x0A13, 0x0A28),
+    (1308, 0x0A2A, 0x0A30),
+    (1315, 0x0A32, 0x0A32),
+    (1316, 0x0A35, 0x0A35),
+    (1317, 0x0A38, 0x0A39),
+    (1319, 0x0A5C, 0x0A5C),
+    (1320, 0x0A66, 0x0A6F),
+    (1330, 0x0A72, 0x0A74),
+    (1333, 0x0A85, 0x0A8D),
+    (1342, 0x0A8F, 0x0A91),
+    (1345, 0x0A93, 0x0AA8),
+    (1367, 0x0AAA, 0x0AB0),
+    (1374, 0x0AB2, 0x0AB3),
+    (1376, 0x0AB5, 0x0AB9),
+    (1381, 0x0ABD, 0x0ABD),
+    (1382, 0x0AD0, 0x0AD0),
+    (1383, 0x0AE0, 0x0AE1),
+    (1385, 0x0AE6, 0x0AEF),
+    (1395, 0x0AF9, 0x0AF9),
+    (1396, 0x0B05, 0x0B0C),
+    (1404, 0x0B0F, 0x0B10),
+    (1406, 0x0B13, 0x0B28),
+    (1428, 0x0B2A, 0x0B30),
+    (1435, 0x0B32, 0x0B33),
+    (1437, 0x0B35, 0x0B39),
+    (1442, 0x0B3D, 0x0B3D),
+    (1443, 0x0B5F, 0x0B61),
+    (1446, 0x0B66, 0x0B6F),
+    (1456, 0x0B71, 0x0B77),
+    (1463, 0x0B83, 0x0B83),
+    (1464, 0x0B85, 0x0B8A),
+    (1470, 0x0B8E, 0x0B90),
+    (1473, 0x0B92, 0x0B93),
+    (1475, 0x0B95, 0x0B95),
+    (1476, 0x0B99, 0x0B9A),
+    (1478, 0x0B9C, 0x0B9C),
+    (1479, 0x0B9E, 0x0B9F),
+    (1481, 0x0BA3, 0x0BA4),
+    (1483, 0x0BA8, 0x0BAA),
+    (1486, 0x0BAE, 0x0BB9),
+    (1498, 0x0BD0, 0x0BD0),
+    (1499, 0x0BE6, 0x0BF2),
+    (1512, 0x0C05, 0x0C0C),
+    (1520, 0x0C0E, 0x0C10),
+    (1523, 0x0C12, 0x0C28),
+    (1546, 0x0C2A, 0x0C39),
+    (1562, 0x0C3D, 0x0C3D),
+    (1563, 0x0C58, 0x0C5A),
+    (1566, 0x0C60, 0x0C61),
+    (1568, 0x0C66, 0x0C6F),
+    (1578, 0x0C78, 0x0C7E),
+    (1585, 0x0C80, 0x0C80),
+    (1586, 0x0C85, 0x0C8C),
+    (1594, 0x0C8E, 0x0C90),
+    (1597, 0x0C92, 0x0CA8),
+    (1620, 0x0CAA, 0x0CB3),
+    (1630, 0x0CB5, 0x0CB9),
+    (1635, 0x0CBD, 0x0CBD),
+    (1636, 0x0CDE, 0x0CDE),
+    (1637, 0x0CE0, 0x0CE1),
+    (1639, 0x0CE6, 0x0CEF),
+    (1649, 0x0CF1, 0x0CF2),
+    (1651, 0x0D05, 0x0D0C),
+    (1659, 0x0D0E, 0x0D10),
+    (1662, 0x0D12, 0x0D3A),
+    (1703, 0x0D3D, 0x0D3D),
+    (1704, 0x0D4E, 0x0D4E),
+    (1705, 0x0D54, 0x0D56),
+    (1708, 0x0D58, 0x0D61),
+    (1718, 0x0D66, 0x0D78),
+    (1737, 0x0D7A, 0x0D7F),
+    (1743, 0x0D85, 0x0D96),
+    (1761, 0x0D9A, 0x0DB1),
+    (1785, 0x0DB3, 0x0DBB),
+    (1794, 0x0DBD, 0x0DBD),
+    (1795, 0x0DC0, 0x0DC6),
+    (1802, 0x0DE6, 0x0DEF),
+    (1812, 0x0E01, 0x0E30),
+    (1860, 0x0E32, 0x0E32),
+    (1861, 0x0E40, 0x0E45),
+    (1867, 0x0E50, 0x0E59),
+    (1877, 0x0E81, 0x0E82),
+    (1879, 0x0E84, 0x0E84),
+    (1880, 0x0E87, 0x0E88),
+    (1882, 0x0E8A, 0x0E8A),
+    (1883, 0x0E8D, 0x0E8D),
+    (1884, 0x0E94, 0x0E97),
+    (1888, 0x0E99, 0x0E9F),
+    (1895, 0x0EA1, 0x0EA3),
+    (1898, 0x0EA5, 0x0EA5),
+    (1899, 0x0EA7, 0x0EA7),
+    (1900, 0x0EAA, 0x0EAB),
+    (1902, 0x0EAD, 0x0EB0),
+    (1906, 0x0EB2, 0x0EB2),
+    (1907, 0x0EBD, 0x0EBD),
+    (1908, 0x0EC0, 0x0EC4),
+    (1913, 0x0ED0, 0x0ED9),
+    (1923, 0x0EDE, 0x0EDF),
+    (1925, 0x0F00, 0x0F00),
+    (1926, 0x0F20, 0x0F33),
+    (1946, 0x0F40, 0x0F42),
+    (1949, 0x0F44, 0x0F47),
+    (1953, 0x0F49, 0x0F4C),
+    (1957, 0x0F4E, 0x0F51),
+    (1961, 0x0F53, 0x0F56),
+    (1965, 0x0F58, 0x0F5B),
+    (1969, 0x0F5D, 0x0F68),
+    (1981, 0x0F6A, 0x0F6C),
+    (1984, 0x0F88, 0x0F8C),
+    (1989, 0x1000, 0x1025),
+    (2027, 0x1027, 0x102A),
+    (2031, 0x103F, 0x1049),
+    (2042, 0x1050, 0x1055),
+];
+/// The 2^3 code points used by Base2048 for the last 1 to 3 bits
+const BASE2048_TAIL_TABLE: &[(u32, u32, u32)] = &[(0, 0x0030, 0x0037)];
+/// The 2^15 code points of qntm's Base32768 repertoire
+const BASE32768_TABLE: &[(u32, u32, u32)] = &[
+    (0, 0x04A0, 0x04BF),
+    (32, 0x0500, 0x051F),
+    (64, 0x0680, 0x06BF),
+    (128, 0x0760, 0x079F),
+    (192, 0x07C0, 0x07DF),
+    (224, 0x1000, 0x101F),
+    (256, 0x10A0, 0x10BF),
+    (288, 0x1100, 0x115F),
+    (384, 0x1180, 0x119F),
+    (416, 0x11E0, 0x123F),
+    (512, 0x1260, 0x127F),
+    (544, 0x12E0, 0x12FF),
+    (576, 0x1320, 0x133F),
+    (608, 0x13A0, 0x13DF),
+    (672, 0x1420, 0x165F),
+    (1248, 0x16A0, 0x16DF),
+    (1312, 0x1780, 0x179F),
+    (1344, 0x1820, 0x185F),
+    (1408, 0x18C0, 0x18DF),
+    (1440, 0x1980, 0x199F),
+    (1472, 0x19E0, 0x19FF),
+    (1504, 0x1A20, 0x1A3F),
+    (1536, 0x1BC0, 0x1BDF),
+    (1568, 0x1C00, 0x1C1F),
+    (1600, 0x1D00, 0x1D1F),
+    (1632, 0x21E0, 0x21FF),
+    (1664, 0x22C0, 0x22DF),
+    (1696, 0x2340, 0x23DF),
+    (1856, 0x2400, 0x241F),
+    (1888, 0x2500, 0x275F),
+    (2496, 0x2780, 0x27BF),
+    (2560, 0x2800, 0x297F),
+    (2944, 0x29A0, 0x29BF),
+    (2976, 0x2A20, 0x2A5F),
+    (3040, 0x2A80, 0x2ABF),
+    (3104, 0x2AE0, 0x2B5F),
+    (3232, 0x2C00, 0x2C1F),
+    (3264, 0x2C80, 0x2CDF),
+    (3360, 0x2D00, 0x2D1F),
+    (3392, 0x2D40, 0x2D5F),
+    (3424, 0x2EA0, 0x2EDF),
+    (3488, 0x31C0, 0x31DF),
+    (3520, 0x3400, 0x4D9F),
+    (10080, 0x4DC0, 0x9FBF),
+    (31072, 0xA000, 0xA47F),
+    (32224, 0xA4A0, 0xA4BF),
+    (32256, 0xA500, 0xA5FF),
+    (32512, 0xA640, 0xA65F),
+    (32544, 0xA6A0, 0xA6DF),
+    (32608, 0xA700, 0xA75F),
+    (32704, 0xA780, 0xA79F),
+    (32736, 0xA840, 0xA85F),
+];
+/// The 2^7 code points used by Base32768 for the last 1 to 7 bits
+const BASE32768_TAIL_TABLE: &[(u32, u32, u32)] = &[(0, 0x0180, 0x019F), (32, 0x0240, 0x029F)];
+/// The 2^16 code points of qntm's Base65536 repertoire
+const BASE65536_TABLE: &[(u32, u32, u32)] = &[
+    (0, 0x3400, 0x4CFF),
+    (6400, 0x4E00, 0x9EFF),
+    (27136, 0xA100, 0xA3FF),
+    (27904, 0xA500, 0xA5FF),
+    (28160, 0x10600, 0x106FF),
+    (28416, 0x12000, 0x122FF),
+    (29184, 0x13000, 0x133FF),
+    (30208, 0x14400, 0x145FF),
+    (30720, 0x16800, 0x169FF),
+    (31232, 0x20000, 0x285FF),
+];
+/// The 2^8 code points used by Base65536 for a last single byte
+const BASE65536_TAIL_TABLE: &[(u32, u32, u32)] = &[(0, 0x1500, 0x15FF)];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    /// Check an encoding against the `pairs` and `bad` files of its corpus in `testdata/qntm`
+    fn check_corpus<const BITS: u32>(encoding: &Qntm<BITS>, name: &str) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/qntm")
+            .join(name);
+        let mut pairs = 0;
+        for entry in fs::read_dir(dir.join("pairs")).unwrap() {
+            let bin = entry.unwrap().path();
+            if bin.extension().is_none_or(|ext| ext != "bin") {
+                continue;
+            }
+            let bytes = fs::read(&bin).unwrap();
+            let text = fs::read_to_string(bin.with_extension("txt")).unwrap();
+            assert_eq!(encoding.encode(&bytes), text, "encoding {}", bin.display());
+            assert_eq!(
+                encoding.decode(&text).unwrap(),
+                bytes,
+                "decoding {}",
+                bin.display()
+            );
+            pairs += 1;
+        }
+        assert!(pairs > 0, "no pairs in {}", dir.display());
+        let mut bads = 0;
+        for entry in fs::read_dir(dir.join("bad")).unwrap() {
+            let bad = entry.unwrap().path();
+            bads += 1;
+            // invalid UTF-8 cannot even be passed to `decode`
+            if let Ok(text) = String::from_utf8(fs::read(&bad).unwrap()) {
+                assert!(
+                    encoding.decode(&text).is_err(),
+                    "decoding {}",
+                    bad.display()
+                );
+            }
+        }
+        assert!(bads > 0, "no bad inputs in {}", dir.display());
+    }
+
+    #[test]
+    fn corpus() {
+        check_corpus(&BASE65536, "base65536");
+        check_corpus(&BASE32768, "base32768");
+        check_corpus(&BASE2048, "base2048");
+    }
+
+    #[test]
+    fn base65536_vectors() {
+        assert_eq!(BASE65536.encode(b""), "");
+        assert_eq!(BASE65536.encode(b"hello world"), "驨ꍬ啯𒁷ꍲᕤ");
+        assert_eq!(BASE65536.decode("驨ꍬ啯𒁷ꍲᕤ").unwrap(), b"hello world");
+        assert_eq!(BASE65536.encode([0x00, 0x00]), "\u{3400}");
+        assert_eq!(BASE65536.encode([0xFF, 0xFF]), "\u{285FF}");
+        assert_eq!(BASE65536.encode([0x01, 0x19]), "\u{4E01}");
+        assert_eq!(BASE65536.encode([0x00]), "\u{1500}");
+        assert_eq!(BASE65536.encode([0xFF]), "\u{15FF}");
+    }
+
+    #[test]
+    fn base32768_vectors() {
+        assert_eq!(BASE32768.encode([0x00, 0x00]), "\u{04A0}\u{025F}");
+        assert_eq!(BASE32768.encode([0x00]), "\u{06BF}");
+        assert_eq!(BASE32768.encode([0xFF]), "\u{A85F}");
+        assert_eq!(BASE32768.encode([0xFF, 0xFF]), "\u{A85F}\u{029F}");
+        assert_eq!(BASE32768.decode("\u{04A0}\u{025F}").unwrap(), [0x00, 0x00]);
+    }
+
+    #[test]
+    fn base2048_vectors() {
+        assert_eq!(BASE2048.encode([0x00]), "F");
+        assert_eq!(BASE2048.encode([0x00, 0x00]), "8\u{0110}");
+        assert_eq!(BASE2048.encode([0xFF, 0xFF]), "\u{1055}\u{1055}");
+        assert_eq!(BASE2048.encode([0x00; 11]), "88888888");
+        assert_eq!(BASE2048.decode("F").unwrap(), [0x00]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+        for len in 0..64 {
+            assert_eq!(
+                BASE2048.decode(BASE2048.encode(&data[..len])).unwrap(),
+                &data[..len]
+            );
+            assert_eq!(
+                BASE32768.decode(BASE32768.encode(&data[..len])).unwrap(),
+                &data[..len]
+            );
+            assert_eq!(
+                BASE65536.decode(BASE65536.encode(&data[..len])).unwrap(),
+                &data[..len]
+            );
+        }
+        assert_eq!(BASE2048.decode(BASE2048.encode(&data)).unwrap(), data);
+        assert_eq!(BASE32768.decode(BASE32768.encode(&data)).unwrap(), data);
+        assert_eq!(BASE65536.decode(BASE65536.encode(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn invalid_input() {
+        // tail code point before the end of the input
+        assert!(matches!(BASE65536.decode("ᕤ驨"), Err(InvalidChar(0, 'ᕤ'))));
+        assert!(matches!(BASE2048.decode("0F"), Err(InvalidChar(0, '0'))));
+        // unknown code point
+        assert!(matches!(BASE2048.decode("F!"), Err(InvalidChar(1, '!'))));
+        // fill bits which are not all set
+        assert!(matches!(BASE2048.decode("8"), Err(InvalidChar(0, '8'))));
+        assert!(matches!(
+            BASE32768.decode("\u{04A0}\u{0180}"),
+            Err(InvalidChar(1, '\u{0180}'))
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::alphabet::Alphabet;
+use crate::InvalidChar;
+
+/// The largest block of bytes converted at once
+const MAX_BLOCK_BYTES: usize = 64;
+/// The largest number of symbols in a block, reached with a radix of 2
+const MAX_BLOCK_SYMBOLS: usize = MAX_BLOCK_BYTES * 8;
+/// The number of 32 bit limbs needed to hold powers of the radix up to a whole block
+const LIMBS: usize = MAX_BLOCK_BYTES / 4 + 2;
+
+/// An encoding using every symbol of an [`Alphabet`] of any size
+///
+/// The input is split into blocks of `block_bytes` bytes, which are converted to `block_symbols`
+/// digits of radix `alphabet.len()`. The block size, up to 64 bytes, is chosen to waste as few bits
+/// as possible. A shorter last block uses the fewest digits able to hold it, followed by a padding
+/// code point if those digits decode to more bytes than were encoded.
+#[derive(Debug, Clone, Copy)]
+pub struct Radix {
+    alphabet: Alphabet,
+    block_bytes: usize,
+    block_symbols: usize,
+}
+
+impl Radix {
+    /// Create an encoding from an alphabet with at least 2 symbols and padding
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alphabet has less than 2 symbols or has no padding
+    pub const fn new(alphabet: Alphabet) -> Self {
+        assert!(alphabet.len() >= 2, "alphabet is too small");
+        assert!(alphabet.has_padding(), "alphabet has no padding");
+        let radix = alphabet.len();
+        let mut block_bytes = 1;
+        let mut block_symbols = symbols_for_bytes(radix, 1);
+        let mut bytes = 2;
+        while bytes <= MAX_BLOCK_BYTES {
+            let symbols = symbols_for_bytes(radix, bytes);
+            if bytes * block_symbols > block_bytes * symbols {
+                block_bytes = bytes;
+                block_symbols = symbols;
+            }
+            bytes += 1;
+        }
+        Self {
+            alphabet,
+            block_bytes,
+            block_symbols,
+        }
+    }
+
+    /// The alphabet of the encoding
+    pub const fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// The number of bytes encoded by a full block
+    pub const fn block_bytes(&self) -> usize {
+        self.block_bytes
+    }
+
+    /// The number of symbols in a full block
+    pub const fn block_symbols(&self) -> usize {
+        self.block_symbols
+    }
+
+    /// The number of bytes held by `symbols` digits, at most one block
+    fn bytes_for_symbols(&self, symbols: usize) -> usize {
+        if symbols == self.block_symbols {
+            return self.block_bytes;
+        }
+        let mut bytes = 0;
+        while bytes < self.block_bytes
+            && symbols_for_bytes(self.alphabet.len(), bytes + 1) <= symbols
+        {
+            bytes += 1;
+        }
+        bytes
+    }
+
+    /// Encode some bytes to a string
+    pub fn encode<T: AsRef<[u8]>>(&self, input: T) -> String {
+        let input = input.as_ref();
+        let radix = self.alphabet.len();
+        let mut out =
+            String::with_capacity(input.len().div_ceil(self.block_bytes) * self.block_symbols * 4);
+        let mut value = [0; MAX_BLOCK_BYTES];
+        let mut digits = [0; MAX_BLOCK_SYMBOLS];
+        for block in input.chunks(self.block_bytes) {
+            let (symbols, bytes) = if block.len() == self.block_bytes {
+                (self.block_symbols, self.block_bytes)
+            } else {
+                let symbols = symbols_for_bytes(radix, block.len());
+                (symbols, self.bytes_for_symbols(symbols))
+            };
+            let value = &mut value[..bytes];
+            value[..block.len()].copy_from_slice(block);
+            value[block.len()..].fill(0);
+            for digit in digits[..symbols].iter_mut().rev() {
+                *digit = div_rem(value, radix);
+            }
+            for &digit in &digits[..symbols] {
+                // every digit is smaller than the radix
+                out.push(self.alphabet.encode(digit).unwrap());
+            }
+            // a digit holds less than 3 bytes, so at most 2 bytes of padding are needed
+            if let Some(padding) = self.alphabet.padding(bytes - block.len()) {
+                out.push(padding);
+            }
+        }
+        out
+    }
+
+    /// Decode an encoded string
+    pub fn decode<T: AsRef<str>>(&self, input: T) -> Result<Vec<u8>, InvalidChar> {
+        let mut string = input.as_ref();
+        let mut padding = None;
+        if let Some((last_char_index, ch)) = string.char_indices().last() {
+            if let Some(bytes) = self.alphabet.decode_padding(ch) {
+                padding = Some((bytes, ch));
+                string = &string[..last_char_index];
+            }
+        }
+        let radix = self.alphabet.len();
+        let mut out = Vec::with_capacity(string.len() / self.block_symbols * self.block_bytes);
+        let mut digits = [0; MAX_BLOCK_SYMBOLS];
+        let mut symbols = 0;
+        let mut block_start = (0, '\0');
+        let mut chars = string.chars().enumerate().peekable();
+        while let Some((idx, ch)) = chars.next() {
+            if symbols == 0 {
+                block_start = (idx, ch);
+            }
+            digits[symbols] = self.alphabet.decode(ch).ok_or(InvalidChar(idx, ch))?;
+            symbols += 1;
+            if symbols == self.block_symbols || chars.peek().is_none() {
+                let bytes = self.bytes_for_symbols(symbols);
+                let start = out.len();
+                out.resize(start + bytes, 0);
+                if bytes == 0 || !mul_add(&mut out[start..], radix, &digits[..symbols]) {
+                    return Err(InvalidChar(block_start.0, block_start.1));
+                }
+                symbols = 0;
+            }
+        }
+        if let Some((padding, ch)) = padding {
+            if padding > out.len() {
+                return Err(InvalidChar(string.chars().count(), ch));
+            }
+            out.truncate(out.len() - padding);
+        }
+        Ok(out)
+    }
+}
+
+/// Divide a big endian number by the radix in place, returning the remainder
+fn div_rem(value: &mut [u8], radix: u32) -> u32 {
+    let mut rem = 0;
+    for byte in value {
+        let acc = (rem << 8) | *byte as u32;
+        *byte = (acc / radix) as u8;
+        rem = acc % radix;
+    }
+    rem
+}
+
+/// Set a zeroed big endian number to the value of the digits. Returns `false` on overflow.
+fn mul_add(value: &mut [u8], radix: u32, digits: &[u32]) -> bool {
+    for &digit in digits {
+        let mut carry = digit as u64;
+        for byte in value.iter_mut().rev() {
+            let acc = *byte as u64 * radix as u64 + carry;
+            *byte = acc as u8;
+            carry = acc >> 8;
+        }
+        if carry != 0 {
+            return false;
+        }
+    }
+    true
+}
+
+/// The fewest digits of `radix` able to hold any value of `bytes` bytes
+const fn symbols_for_bytes(radix: u32, bytes: usize) -> usize {
+    // little endian limbs of `radix^symbols`
+    let mut capacity = [0u32; LIMBS];
+    capacity[0] = 1;
+    let mut symbols = 0;
+    while !exceeds_bits(&capacity, bytes * 8) {
+        let mut carry = 0;
+        let mut limb = 0;
+        while limb < LIMBS {
+            let acc = capacity[limb] as u64 * radix as u64 + carry;
+            capacity[limb] = acc as u32;
+            carry = acc >> 32;
+            limb += 1;
+        }
+        symbols += 1;
+    }
+    symbols
+}
+
+/// Whether a little endian number is at least `2^bits`
+const fn exceeds_bits(value: &[u32; LIMBS], bits: usize) -> bool {
+    let mut limb = LIMBS;
+    while limb > 0 {
+        limb -= 1;
+        if value[limb] != 0 {
+            return limb * 32 + (32 - value[limb].leading_zeros() as usize) > bits;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE62: Alphabet = Alphabet::new(&[(0, 0x30, 0x39), (10, 0x41, 0x5A), (36, 0x61, 0x7A)])
+        .with_padding('-' as u32, '_' as u32);
+
+    #[test]
+    fn block_sizes() {
+        let radix = Radix::new(BASE62);
+        assert_eq!((radix.block_bytes(), radix.block_symbols()), (32, 43));
+        let radix = Radix::new(Alphabet::BASE131072.truncate(1 << 16));
+        assert_eq!((radix.block_bytes(), radix.block_symbols()), (2, 1));
+        let radix = Radix::new(Alphabet::BASE131072.truncate(100_000));
+        assert_eq!((radix.block_bytes(), radix.block_symbols()), (56, 27));
+    }
+
+    #[test]
+    fn encoding() {
+        let data = (0..=255).rev().cycle().take(1000).collect::<Vec<u8>>();
+        for alphabet in [
+            BASE62,
+            Alphabet::BASE131072.truncate(2),
+            Alphabet::BASE131072.truncate(3),
+            Alphabet::BASE131072.truncate(1 << 16),
+            Alphabet::BASE131072.truncate(100_000),
+            Alphabet::BASE131072,
+        ] {
+            let radix = Radix::new(alphabet);
+            for len in (0..40).chain([1000]) {
+                let encoded = radix.encode(&data[..len]);
+                assert_eq!(radix.decode(&encoded).unwrap(), &data[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn uses_whole_alphabet() {
+        let data = [0xA5; 1400];
+        let radix = Radix::new(Alphabet::BASE131072.truncate(100_000));
+        assert_eq!(radix.encode(data).chars().count(), 675);
+        assert_eq!(crate::BASE65536.encode(data).chars().count(), 700);
+        assert!(crate::BASE65536.encode([0xA5; 1401]).chars().count() > 700);
+        assert_eq!(radix.encode([0xA5; 1401]).chars().count(), 677);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let radix = Radix::new(BASE62);
+        assert!(matches!(radix.decode("zzz"), Err(InvalidChar(0, 'z'))));
+        assert!(matches!(radix.decode("4!"), Err(InvalidChar(1, '!'))));
+        assert!(matches!(radix.decode("-"), Err(InvalidChar(0, '-'))));
+        assert_eq!(radix.decode("47").unwrap(), [0xFF]);
+    }
+}
@@ -0,0 +1,459 @@
+//! Vectorized conversion of whole blocks, using the best instructions detected at runtime
+//!
+//! Every step of a block runs on its 8 symbols at once: splitting 17 bytes into symbol values
+//! with fixed shuffles and shifts, looking them up in the page tables, and emitting and validating
+//! UTF-8. AVX2 loads the table entries with gathers, while SSE4.1 and NEON, which have no gathers,
+//! compute the table indices in vectors and load the entries lane by lane. The compact tables are
+//! walked rather than indexed, so with `compact-tables` the lookups are scalar. CPUs without
+//! SSE4.1 or NEON leave everything to the scalar path.
+
+use crate::block::{BLOCK_BYTES, BLOCK_SYMBOLS};
+#[cfg(feature = "compact-tables")]
+use crate::B17;
+
+#[cfg(target_arch = "aarch64")]
+mod neon;
+#[cfg(target_arch = "x86_64")]
+mod x86;
+
+/// The most UTF-8 bytes a block of symbols can take up
+const MAX_BLOCK_UTF8: usize = BLOCK_SYMBOLS * 4;
+/// The bytes needed to decode a block of chars: a block of the longest chars, plus the 3 bytes
+/// past the start of its last char which are read along with it
+const DECODE_WINDOW: usize = MAX_BLOCK_UTF8 + 3;
+
+/// Per UTF-8 width, the bits of the lead byte compared to `LEAD_BITS`
+const LEAD_MASKS: [u32; 4] = [0x80, 0xE0, 0xF0, 0xF8];
+/// Per UTF-8 width, the expected bits of the lead byte
+const LEAD_BITS: [u32; 4] = [0x00, 0xC0, 0xE0, 0xF0];
+/// Per UTF-8 width, the payload bits of a char's big endian bytes at the bottom of a lane
+const PAYLOAD_MASKS: [u32; 4] = [0x7F, 0x1F3F, 0x0F_3F3F, 0x073F_3F3F];
+/// Per UTF-8 width, the marker bits of a char's big endian bytes at the bottom of a lane
+const MARKERS: [u32; 4] = [0x00, 0xC080, 0xE0_8080, 0xF080_8080];
+/// Per UTF-8 width, the smallest code point which is not an overlong encoding
+const MIN_CODE_POINTS: [u32; 4] = [0, 0x80, 0x800, 0x10000];
+/// Per UTF-8 width, the byte shuffle moving a char's bytes from the top to the bottom of its lane
+const RIGHT_ALIGN: [u32; 4] = [0x8080_8003, 0x8080_0302, 0x8003_0201, 0x0302_0100];
+
+/// Byte shuffles packing the UTF-8 bytes at the bottom of 4 lanes, indexed by the widths minus
+/// one of the lanes, 2 bits each. Indices with the high bit set zero the unused bytes.
+static COMPRESS: [[u8; 16]; 256] = compress_table();
+
+const fn compress_table() -> [[u8; 16]; 256] {
+    let mut table = [[0x80; 16]; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut out = 0;
+        let mut lane = 0;
+        while lane < 4 {
+            let mut byte = ((idx >> (2 * lane)) & 3) + 1;
+            while byte > 0 {
+                byte -= 1;
+                table[idx][out] = (lane * 4 + byte) as u8;
+                out += 1;
+            }
+            lane += 1;
+        }
+        idx += 1;
+    }
+    table
+}
+
+/// The vector instructions of one instruction set, working on blocks of 8 `u32` lanes
+///
+/// The methods are inlined into entry points compiled with the matching target features.
+trait Vectors {
+    type Lanes: Copy;
+
+    unsafe fn load(lanes: &[u32; BLOCK_SYMBOLS]) -> Self::Lanes;
+
+    #[cfg(feature = "compact-tables")]
+    unsafe fn store(lanes: Self::Lanes) -> [u32; BLOCK_SYMBOLS];
+
+    /// Split a block of bytes into its symbol values
+    unsafe fn unpack(block: &[u8; BLOCK_BYTES]) -> Self::Lanes;
+
+    /// Join symbol values, which must be less than 2^17, back into a block of bytes
+    unsafe fn pack(values: Self::Lanes) -> [u8; BLOCK_BYTES];
+
+    /// Write the UTF-8 encoding of the code points, returning its length. Up to
+    /// `MAX_BLOCK_UTF8` bytes are overwritten.
+    unsafe fn encode_utf8(code_points: Self::Lanes, out: *mut u8) -> usize;
+
+    /// A bit mask of the first 32 bytes of the window which are not UTF-8 continuation bytes
+    unsafe fn char_starts(window: &[u8; DECODE_WINDOW]) -> u32;
+
+    /// The code points of the chars starting the big endian `words`, which are `widths` bytes
+    /// long, or `None` if any of them is not valid UTF-8
+    unsafe fn decode_utf8(words: Self::Lanes, widths: Self::Lanes) -> Option<Self::Lanes>;
+
+    /// The code points of symbol values, indexing the page tables in every lane
+    #[cfg(not(feature = "compact-tables"))]
+    unsafe fn encode_lookup(values: Self::Lanes) -> Self::Lanes;
+
+    /// The symbol values of valid code points, or `None` if any of them is not part of the
+    /// encoding
+    #[cfg(not(feature = "compact-tables"))]
+    unsafe fn decode_lookup(code_points: Self::Lanes) -> Option<Self::Lanes>;
+
+    /// The code points of symbol values, looked up lane by lane as the compact tables are walked
+    /// instead of indexed
+    #[cfg(feature = "compact-tables")]
+    #[inline(always)]
+    unsafe fn encode_lookup(values: Self::Lanes) -> Self::Lanes {
+        let mut lanes = Self::store(values);
+        for lane in &mut lanes {
+            *lane = B17(*lane).encode() as u32;
+        }
+        Self::load(&lanes)
+    }
+
+    /// The symbol values of valid code points, or `None` if any of them is not part of the
+    /// encoding
+    #[cfg(feature = "compact-tables")]
+    #[inline(always)]
+    unsafe fn decode_lookup(code_points: Self::Lanes) -> Option<Self::Lanes> {
+        let mut lanes = Self::store(code_points);
+        for lane in &mut lanes {
+            *lane = B17::decode(char::from_u32_unchecked(*lane))?.0;
+        }
+        Some(Self::load(&lanes))
+    }
+}
+
+/// Split the next block of chars off a window, given the mask of its char starts. Returns the big
+/// endian words starting at every char, the widths of the chars and the length of the block, or
+/// `None` if the window does not start with a block of chars of 1 to 4 bytes.
+#[inline(always)]
+fn split_chars(
+    window: &[u8; DECODE_WINDOW],
+    starts: u32,
+) -> Option<([u32; BLOCK_SYMBOLS], [u32; BLOCK_SYMBOLS], usize)> {
+    if starts & 1 == 0 || starts.count_ones() < BLOCK_SYMBOLS as u32 {
+        return None;
+    }
+    let mut words = [0; BLOCK_SYMBOLS];
+    let mut widths = [0; BLOCK_SYMBOLS];
+    let mut rest = starts & (starts - 1);
+    let mut start = 0;
+    let mut too_wide = false;
+    for idx in 0..BLOCK_SYMBOLS {
+        // without a next start the last char ends with the mask, which decode_utf8 checks against
+        // the width given by its lead byte
+        let end = rest.trailing_zeros() as usize;
+        rest &= rest.wrapping_sub(1);
+        // chars only start within the bytes covered by the mask
+        if start >= MAX_BLOCK_UTF8 {
+            return None;
+        }
+        too_wide |= end - start > 4;
+        words[idx] = u32::from_be_bytes(window[start..][..4].try_into().unwrap());
+        widths[idx] = (end - start) as u32;
+        start = end;
+    }
+    if too_wide {
+        return None;
+    }
+    Some((words, widths, start))
+}
+
+#[inline(always)]
+unsafe fn encode_blocks_with<V: Vectors>(input: &[u8], out: &mut Vec<u8>) -> usize {
+    let blocks = input.chunks_exact(BLOCK_BYTES);
+    let consumed = input.len() - blocks.remainder().len();
+    out.reserve(blocks.len() * MAX_BLOCK_UTF8);
+    let mut len = out.len();
+    for block in blocks {
+        let values = V::unpack(block.try_into().unwrap());
+        let code_points = V::encode_lookup(values);
+        len += V::encode_utf8(code_points, out.as_mut_ptr().add(len));
+    }
+    out.set_len(len);
+    consumed
+}
+
+#[inline(always)]
+unsafe fn decode_blocks_with<V: Vectors>(input: &[u8], out: &mut Vec<u8>) -> (usize, usize) {
+    let mut consumed = 0;
+    let mut chars = 0;
+    while let Some(window) = input.get(consumed..consumed + DECODE_WINDOW) {
+        let window = window.try_into().unwrap();
+        let Some((words, widths, len)) = split_chars(window, V::char_starts(window)) else {
+            break;
+        };
+        let Some(code_points) = V::decode_utf8(V::load(&words), V::load(&widths)) else {
+            break;
+        };
+        let Some(values) = V::decode_lookup(code_points) else {
+            break;
+        };
+        crate::reserve_decoded(out, BLOCK_BYTES);
+        out.extend_from_slice(&V::pack(values));
+        consumed += len;
+        chars += BLOCK_SYMBOLS;
+    }
+    (consumed, chars)
+}
+
+/// Encode the whole blocks of the input, appending the UTF-8 to `out`. Returns the number of bytes
+/// consumed, which is 0 if the CPU has no supported vector instructions.
+pub(crate) fn encode_blocks(input: &[u8], out: &mut Vec<u8>) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { x86::encode_blocks_avx2(input, out) };
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return unsafe { x86::encode_blocks_sse41(input, out) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        return unsafe { neon::encode_blocks(input, out) };
+    }
+    let _ = (input, out);
+    0
+}
+
+/// Decode leading blocks of valid UTF-8 encoded chars which contain no padding, appending the bytes
+/// to `out`. Returns the number of bytes and chars consumed, leaving everything from the first
+/// block which is invalid or not followed by enough input to the scalar path.
+pub(crate) fn decode_blocks(input: &[u8], out: &mut Vec<u8>) -> (usize, usize) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { x86::decode_blocks_avx2(input, out) };
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return unsafe { x86::decode_blocks_sse41(input, out) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        return unsafe { neon::decode_blocks(input, out) };
+    }
+    let _ = (input, out);
+    (0, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::utf8::Utf8Chars;
+    use crate::{decode_chars, DecodeError, B17, BASE131072, PAD1};
+
+    type EncodeBlocks = unsafe fn(&[u8], &mut Vec<u8>) -> usize;
+    type DecodeBlocks = unsafe fn(&[u8], &mut Vec<u8>) -> (usize, usize);
+
+    /// Every implementation supported by the CPU running the tests
+    fn implementations() -> Vec<(&'static str, EncodeBlocks, DecodeBlocks)> {
+        let mut implementations: Vec<(&'static str, EncodeBlocks, DecodeBlocks)> = Vec::new();
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("sse4.1") {
+                implementations.push((
+                    "sse4.1",
+                    x86::encode_blocks_sse41,
+                    x86::decode_blocks_sse41,
+                ));
+            }
+            if is_x86_feature_detected!("avx2") {
+                implementations.push(("avx2", x86::encode_blocks_avx2, x86::decode_blocks_avx2));
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            implementations.push(("neon", neon::encode_blocks, neon::decode_blocks));
+        }
+        implementations
+    }
+
+    fn scalar_encode(input: &[u8]) -> String {
+        crate::b17s(input).map(B17::encode).collect()
+    }
+
+    fn scalar_decode(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut bytes = Vec::new();
+        decode_chars(Utf8Chars::new(input, 0), &mut bytes, 0)?;
+        Ok(bytes)
+    }
+
+    /// Random symbols, a quarter each of 1 and 2 byte UTF-8 chars and the rest uniform
+    fn random_symbols(state: &mut u64, len: usize) -> String {
+        (0..len)
+            .map(|_| {
+                let value = match random(state) % 4 {
+                    0 => random(state) % 95,
+                    1 => 95 + random(state) % 1800,
+                    _ => random(state) % (1 << 17),
+                };
+                B17(value as u32).encode()
+            })
+            .collect()
+    }
+
+    /// Random encoded input, of which three quarters are corrupted in some way
+    fn random_input(state: &mut u64) -> Vec<u8> {
+        let len = random(state) as usize % 100;
+        let mut encoded = random_symbols(state, len);
+        let boundaries = encoded
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        let boundary = match boundaries.len() {
+            0 => 0,
+            len => boundaries[random(state) as usize % len],
+        };
+        match random(state) % 4 {
+            0 => {}
+            1 => encoded.insert(boundary, char::from_u32(PAD1).unwrap()),
+            2 => encoded.insert(boundary, char::from_u32(random(state) as u32 % 32).unwrap()),
+            _ => {
+                let mut encoded = encoded.into_bytes();
+                if !encoded.is_empty() {
+                    let idx = random(state) as usize % encoded.len();
+                    encoded[idx] = random(state) as u8;
+                }
+                return encoded;
+            }
+        }
+        encoded.into_bytes()
+    }
+
+    #[test]
+    fn split_chars_within_mask() {
+        let window = core::array::from_fn(|idx| idx as u8);
+        // eight 4 byte chars, the last ending with the mask
+        let (words, widths, len) = split_chars(&window, 0x1111_1111).unwrap();
+        assert_eq!(widths, [4; BLOCK_SYMBOLS]);
+        assert_eq!(len, MAX_BLOCK_UTF8);
+        assert_eq!(words[7], u32::from_be_bytes([28, 29, 30, 31]));
+        assert_eq!(split_chars(&window, u32::MAX).unwrap().2, BLOCK_SYMBOLS);
+        // the last char starts at the last byte of the mask, after a char which is too wide
+        assert_eq!(split_chars(&window, 0xFE00_0001), None);
+        assert_eq!(split_chars(&window, 0x0111_1111), None);
+        assert_eq!(split_chars(&window, 0x1111_1110), None);
+        let mut state = 0x6A09_E667_F3BC_C908;
+        for _ in 0..10_000 {
+            let starts = random(&mut state) as u32 | random(&mut state) as u32 | 1;
+            if let Some((words, widths, len)) = split_chars(&window, starts) {
+                let mut start = 0;
+                for (word, width) in words.into_iter().zip(widths) {
+                    assert!((1..=4).contains(&width));
+                    assert_eq!(word.to_be_bytes()[0], start as u8);
+                    start += width as usize;
+                }
+                assert_eq!(start, len);
+                assert!(len <= MAX_BLOCK_UTF8);
+            }
+        }
+    }
+
+    #[test]
+    fn encoding_matches_scalar() {
+        let mut state = 0x0123_4567_89AB_CDEF;
+        for (name, encode_blocks, _) in implementations() {
+            for _ in 0..1000 {
+                let len = random(&mut state) as usize % 100;
+                let symbols = random_symbols(&mut state, len);
+                let data = BASE131072.decode(symbols).unwrap();
+                let mut out = b"prefix".to_vec();
+                let consumed = unsafe { encode_blocks(&data, &mut out) };
+                assert_eq!(consumed, data.len() / BLOCK_BYTES * BLOCK_BYTES, "{name}");
+                assert_eq!(out[..6], *b"prefix");
+                assert_eq!(
+                    out[6..],
+                    *scalar_encode(&data[..consumed]).as_bytes(),
+                    "{name}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn decoding_matches_scalar() {
+        let mut state = 0xFEDC_BA98_7654_3210;
+        for (name, _, decode_blocks) in implementations() {
+            for round in 0..4000 {
+                let input = random_input(&mut state);
+                let mut out = Vec::new();
+                let (consumed, chars) = unsafe { decode_blocks(&input, &mut out) };
+                let prefix = core::str::from_utf8(&input[..consumed]).unwrap();
+                assert_eq!(prefix.chars().count(), chars, "{name} {round}");
+                assert_eq!(chars % BLOCK_SYMBOLS, 0, "{name} {round}");
+                assert_eq!(Ok(out), scalar_decode(prefix.as_bytes()), "{name} {round}");
+                // valid input is only left to the scalar path from the padding or the last window
+                if let Ok(input) = core::str::from_utf8(&input) {
+                    if BASE131072.decode(input).is_ok() {
+                        let rest = &input[consumed..];
+                        let pad = char::from_u32(PAD1).unwrap();
+                        assert!(
+                            rest.len() < DECODE_WINDOW || rest.contains(pad),
+                            "{name} {round}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lookups_match_scalar() {
+        let symbols = (0..1 << 17)
+            .map(|value| B17(value).encode())
+            .collect::<String>();
+        let data = BASE131072.decode(&symbols).unwrap();
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+        for (name, encode_blocks, decode_blocks) in implementations() {
+            let mut out = Vec::new();
+            assert_eq!(
+                unsafe { encode_blocks(&data, &mut out) },
+                data.len(),
+                "{name}"
+            );
+            assert_eq!(out, symbols.as_bytes(), "{name}");
+            let mut out = Vec::new();
+            let (consumed, _) = unsafe { decode_blocks(symbols.as_bytes(), &mut out) };
+            assert!(symbols.len() - consumed < DECODE_WINDOW, "{name}");
+            assert_eq!(data[..out.len()], out, "{name}");
+            // any scalar value in a block, most of which are not part of the encoding
+            for round in 0..2000 {
+                let mut chars = random_symbols(&mut state, 24).chars().collect::<Vec<_>>();
+                let idx = random(&mut state) as usize % chars.len();
+                chars[idx] = loop {
+                    if let Some(ch) = char::from_u32(random(&mut state) as u32 % 0x11_0000) {
+                        break ch;
+                    }
+                };
+                let input = chars.into_iter().collect::<String>();
+                let mut out = Vec::new();
+                let (consumed, _) = unsafe { decode_blocks(input.as_bytes(), &mut out) };
+                assert_eq!(
+                    Ok(out),
+                    scalar_decode(&input.as_bytes()[..consumed]),
+                    "{name} {round}"
+                );
+                if BASE131072.decode(&input).is_err() {
+                    let bad = input.char_indices().nth(idx).unwrap().0;
+                    assert!(consumed <= bad, "{name} {round}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dispatch_matches_scalar() {
+        let mut state = 0x5DEE_CE66_D1CE_4E5B;
+        for _ in 0..2000 {
+            let input = random_input(&mut state);
+            assert_eq!(crate::decode_utf8_bytes(&input), scalar_decode(&input));
+            if let Ok(input) = core::str::from_utf8(&input) {
+                assert_eq!(crate::decode(input), BASE131072.decode(input));
+                if let Ok(data) = crate::decode(input) {
+                    assert_eq!(crate::encode(&data), BASE131072.encode(&data));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use core::arch::aarch64::*;
+
+use super::{
+    Vectors, COMPRESS, DECODE_WINDOW, LEAD_BITS, LEAD_MASKS, MARKERS, MIN_CODE_POINTS,
+    PAYLOAD_MASKS,
+};
+use crate::block::{BLOCK_BYTES, BLOCK_SYMBOLS};
+#[cfg(not(feature = "compact-tables"))]
+use crate::lookup_table::{
+    DECODE_BLOCKS, DECODE_PAGE_BASES, DECODE_PAGE_BLOCKS, ENCODE_BLOCKS, ENCODE_PAGE_BASES,
+    ENCODE_PAGE_BLOCKS, INVALID, PAGE_BITS,
+};
+#[cfg(not(feature = "compact-tables"))]
+use crate::PAGE_MASK;
+
+/// NEON, with the lanes split over two 128 bit vectors
+struct Neon;
+
+#[inline(always)]
+unsafe fn table(values: [u32; 4]) -> uint8x16_t {
+    vreinterpretq_u8_u32(vld1q_u32(values.as_ptr()))
+}
+
+/// Look up the lanes of `index`, which must be less than 4, in a table of 4 lanes
+#[inline(always)]
+unsafe fn by_index(table: uint8x16_t, index: uint32x4_t) -> uint32x4_t {
+    let bytes = vmlaq_u32(vdupq_n_u32(0x0302_0100), index, vdupq_n_u32(0x0404_0404));
+    vreinterpretq_u32_u8(vqtbl1q_u8(table, vreinterpretq_u8_u32(bytes)))
+}
+
+#[inline(always)]
+unsafe fn encode_utf8_half(code_points: uint32x4_t, out: *mut u8) -> usize {
+    let zero = vdupq_n_u32(0);
+    // one less than the UTF-8 width of every code point
+    let widths = vsubq_u32(
+        vsubq_u32(
+            vsubq_u32(zero, vcgtq_u32(code_points, vdupq_n_u32(0x7F))),
+            vcgtq_u32(code_points, vdupq_n_u32(0x7FF)),
+        ),
+        vcgtq_u32(code_points, vdupq_n_u32(0xFFFF)),
+    );
+    let payload = vorrq_u32(
+        vorrq_u32(
+            vandq_u32(code_points, vdupq_n_u32(0x3F)),
+            vandq_u32(vshlq_n_u32::<2>(code_points), vdupq_n_u32(0x3F00)),
+        ),
+        vorrq_u32(
+            vandq_u32(vshlq_n_u32::<4>(code_points), vdupq_n_u32(0x3F_0000)),
+            vandq_u32(vshlq_n_u32::<6>(code_points), vdupq_n_u32(0x3F00_0000)),
+        ),
+    );
+    let utf8 = vorrq_u32(payload, by_index(table(MARKERS), widths));
+    // ASCII does not fit the 6 bit payloads of the longer widths
+    let utf8 = vbslq_u32(vceqq_u32(widths, zero), code_points, utf8);
+    let mut lanes = [0u32; 4];
+    vst1q_u32(lanes.as_mut_ptr(), widths);
+    let index = lanes[0] | lanes[1] << 2 | lanes[2] << 4 | lanes[3] << 6;
+    let shuffle = vld1q_u8(COMPRESS[index as usize].as_ptr());
+    vst1q_u8(out, vqtbl1q_u8(vreinterpretq_u8_u32(utf8), shuffle));
+    (lanes.iter().sum::<u32>() + 4) as usize
+}
+
+/// The code points of half a block of chars, with a mask of the lanes which are valid UTF-8
+#[inline(always)]
+unsafe fn decode_utf8_half(words: uint32x4_t, widths: uint32x4_t) -> (uint32x4_t, uint32x4_t) {
+    let zero = vdupq_n_u32(0);
+    let widths = vsubq_u32(widths, vdupq_n_u32(1));
+    // shift the bytes of every char to the bottom of its lane
+    let shifts = vsubq_u32(vshlq_n_u32::<3>(widths), vdupq_n_u32(24));
+    let aligned = vshlq_u32(words, vreinterpretq_s32_u32(shifts));
+    let lead = vshrq_n_u32::<24>(words);
+    let lead_ok = vceqq_u32(
+        vandq_u32(lead, by_index(table(LEAD_MASKS), widths)),
+        by_index(table(LEAD_BITS), widths),
+    );
+    let payload = vandq_u32(aligned, by_index(table(PAYLOAD_MASKS), widths));
+    let code_points = vorrq_u32(
+        vorrq_u32(
+            vandq_u32(payload, vdupq_n_u32(0x3F)),
+            vandq_u32(vshrq_n_u32::<2>(payload), vdupq_n_u32(0xFC0)),
+        ),
+        vorrq_u32(
+            vandq_u32(vshrq_n_u32::<4>(payload), vdupq_n_u32(0x3_F000)),
+            vandq_u32(vshrq_n_u32::<6>(payload), vdupq_n_u32(0x1C_0000)),
+        ),
+    );
+    let code_points = vbslq_u32(vceqq_u32(widths, zero), payload, code_points);
+    let valid = vandq_u32(
+        lead_ok,
+        vandq_u32(
+            vcgeq_u32(code_points, by_index(table(MIN_CODE_POINTS), widths)),
+            vcltq_u32(code_points, vdupq_n_u32(0x11_0000)),
+        ),
+    );
+    let surrogate = vandq_u32(
+        vcgeq_u32(code_points, vdupq_n_u32(0xD800)),
+        vcleq_u32(code_points, vdupq_n_u32(0xDFFF)),
+    );
+    (code_points, vbicq_u32(valid, surrogate))
+}
+
+#[inline(always)]
+unsafe fn char_starts_half(bytes: *const u8) -> u32 {
+    const WEIGHTS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+    // continuation bytes are 0x80 to 0xBF, the smallest signed bytes
+    let starts = vcgtq_s8(vreinterpretq_s8_u8(vld1q_u8(bytes)), vdupq_n_s8(-65));
+    let bits = vandq_u8(starts, vld1q_u8(WEIGHTS.as_ptr()));
+    vaddv_u8(vget_low_u8(bits)) as u32 | (vaddv_u8(vget_high_u8(bits)) as u32) << 8
+}
+
+/// The entries of a table at the lanes of `index`, which must be in bounds. NEON has no gathers,
+/// so the entries are loaded one by one.
+#[cfg(not(feature = "compact-tables"))]
+#[inline(always)]
+unsafe fn gather<T: Copy + Into<u32>>(table: &[T], index: uint32x4_t) -> uint32x4_t {
+    let mut lanes = [0u32; 4];
+    vst1q_u32(lanes.as_mut_ptr(), index);
+    vld1q_u32(lanes.map(|idx| table[idx as usize].into()).as_ptr())
+}
+
+/// The code points of half a block of symbol values
+#[cfg(not(feature = "compact-tables"))]
+#[inline(always)]
+unsafe fn encode_lookup_half(values: uint32x4_t) -> uint32x4_t {
+    let pages = vshrq_n_u32::<{ PAGE_BITS as i32 }>(values);
+    let blocks = gather(ENCODE_PAGE_BLOCKS, pages);
+    let offsets = vorrq_u32(
+        vshlq_n_u32::<{ PAGE_BITS as i32 }>(blocks),
+        vandq_u32(values, vdupq_n_u32(PAGE_MASK)),
+    );
+    vaddq_u32(
+        gather(ENCODE_PAGE_BASES, pages),
+        gather(ENCODE_BLOCKS.as_flattened(), offsets),
+    )
+}
+
+/// The symbol values of half a block of code points, with a mask of the lanes which are part of
+/// the encoding
+#[cfg(not(feature = "compact-tables"))]
+#[inline(always)]
+unsafe fn decode_lookup_half(code_points: uint32x4_t) -> (uint32x4_t, uint32x4_t) {
+    let pages = vshrq_n_u32::<{ PAGE_BITS as i32 }>(code_points);
+    // code points past the last page are looked up in it and then rejected
+    let clamped = vminq_u32(pages, vdupq_n_u32(DECODE_PAGE_BLOCKS.len() as u32 - 1));
+    let blocks = gather(DECODE_PAGE_BLOCKS, clamped);
+    let offsets = vorrq_u32(
+        vshlq_n_u32::<{ PAGE_BITS as i32 }>(blocks),
+        vandq_u32(code_points, vdupq_n_u32(PAGE_MASK)),
+    );
+    let deltas = gather(DECODE_BLOCKS.as_flattened(), offsets);
+    let valid = vbicq_u32(
+        vceqq_u32(pages, clamped),
+        vceqq_u32(deltas, vdupq_n_u32(INVALID as u32)),
+    );
+    (vaddq_u32(gather(DECODE_PAGE_BASES, clamped), deltas), valid)
+}
+
+impl Vectors for Neon {
+    type Lanes = (uint32x4_t, uint32x4_t);
+
+    #[inline(always)]
+    unsafe fn load(lanes: &[u32; BLOCK_SYMBOLS]) -> Self::Lanes {
+        let ptr = lanes.as_ptr();
+        (vld1q_u32(ptr), vld1q_u32(ptr.add(4)))
+    }
+
+    #[cfg(feature = "compact-tables")]
+    #[inline(always)]
+    unsafe fn store(lanes: Self::Lanes) -> [u32; BLOCK_SYMBOLS] {
+        let mut out = [0; BLOCK_SYMBOLS];
+        let ptr = out.as_mut_ptr();
+        vst1q_u32(ptr, lanes.0);
+        vst1q_u32(ptr.add(4), lanes.1);
+        out
+    }
+
+    #[inline(always)]
+    unsafe fn unpack(block: &[u8; BLOCK_BYTES]) -> Self::Lanes {
+        const LO: [u8; 16] = [2, 1, 0, 255, 4, 3, 2, 255, 6, 5, 4, 255, 8, 7, 6, 255];
+        const HI: [u8; 16] = [
+            9, 8, 7, 255, 11, 10, 9, 255, 13, 12, 11, 255, 15, 14, 13, 255,
+        ];
+        const SHIFTS: [i32; 8] = [-7, -6, -5, -4, -3, -2, -1, 0];
+        let ptr = block.as_ptr();
+        // symbol `i` lies within the 3 bytes from byte `2 * i`, the second half is loaded from the
+        // second byte to reach the last byte of the block
+        let lo = vqtbl1q_u8(vld1q_u8(ptr), vld1q_u8(LO.as_ptr()));
+        let hi = vqtbl1q_u8(vld1q_u8(ptr.add(1)), vld1q_u8(HI.as_ptr()));
+        // symbol `i` starts `i` bits into its bytes
+        let mask = vdupq_n_u32(0x1FFFF);
+        (
+            vandq_u32(
+                vshlq_u32(vreinterpretq_u32_u8(lo), vld1q_s32(SHIFTS.as_ptr())),
+                mask,
+            ),
+            vandq_u32(
+                vshlq_u32(vreinterpretq_u32_u8(hi), vld1q_s32(SHIFTS.as_ptr().add(4))),
+                mask,
+            ),
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn pack(values: Self::Lanes) -> [u8; BLOCK_BYTES] {
+        const SHIFTS: [i32; 8] = [7, 6, 5, 4, 3, 2, 1, 0];
+        const ENDS: [u8; 16] = [2, 1, 6, 5, 10, 9, 14, 13, 18, 17, 22, 21, 26, 25, 30, 29];
+        const STARTS: [u8; 16] = [
+            255, 255, 0, 255, 4, 255, 8, 255, 12, 255, 16, 255, 20, 255, 24, 255,
+        ];
+        let lo = vshlq_u32(values.0, vld1q_s32(SHIFTS.as_ptr()));
+        let hi = vshlq_u32(values.1, vld1q_s32(SHIFTS.as_ptr().add(4)));
+        let lanes = uint8x16x2_t(vreinterpretq_u8_u32(lo), vreinterpretq_u8_u32(hi));
+        // the byte where one symbol ends and the next starts is shared between their lanes
+        let bytes = vorrq_u8(
+            vqtbl2q_u8(lanes, vld1q_u8(ENDS.as_ptr())),
+            vqtbl2q_u8(lanes, vld1q_u8(STARTS.as_ptr())),
+        );
+        let mut block = [0; BLOCK_BYTES];
+        vst1q_u8(block.as_mut_ptr(), bytes);
+        block[16] = vgetq_lane_u32::<3>(values.1) as u8;
+        block
+    }
+
+    #[inline(always)]
+    unsafe fn encode_utf8(code_points: Self::Lanes, out: *mut u8) -> usize {
+        let len = encode_utf8_half(code_points.0, out);
+        len + encode_utf8_half(code_points.1, out.add(len))
+    }
+
+    #[inline(always)]
+    unsafe fn char_starts(window: &[u8; DECODE_WINDOW]) -> u32 {
+        char_starts_half(window.as_ptr()) | char_starts_half(window.as_ptr().add(16)) << 16
+    }
+
+    #[inline(always)]
+    unsafe fn decode_utf8(words: Self::Lanes, widths: Self::Lanes) -> Option<Self::Lanes> {
+        let (lo, lo_valid) = decode_utf8_half(words.0, widths.0);
+        let (hi, hi_valid) = decode_utf8_half(words.1, widths.1);
+        if vminvq_u32(vandq_u32(lo_valid, hi_valid)) == u32::MAX {
+            Some((lo, hi))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(feature = "compact-tables"))]
+    #[inline(always)]
+    unsafe fn encode_lookup(values: Self::Lanes) -> Self::Lanes {
+        (encode_lookup_half(values.0), encode_lookup_half(values.1))
+    }
+
+    #[cfg(not(feature = "compact-tables"))]
+    #[inline(always)]
+    unsafe fn decode_lookup(code_points: Self::Lanes) -> Option<Self::Lanes> {
+        let (lo, lo_valid) = decode_lookup_half(code_points.0);
+        let (hi, hi_valid) = decode_lookup_half(code_points.1);
+        if vminvq_u32(vandq_u32(lo_valid, hi_valid)) == u32::MAX {
+            Some((lo, hi))
+        } else {
+            None
+        }
+    }
+}
+
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn encode_blocks(input: &[u8], out: &mut Vec<u8>) -> usize {
+    super::encode_blocks_with::<Neon>(input, out)
+}
+
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn decode_blocks(input: &[u8], out: &mut Vec<u8>) -> (usize, usize) {
+    super::decode_blocks_with::<Neon>(input, out)
+}
@@ -0,0 +1,469 @@
+use core::arch::x86_64::*;
+
+use super::{
+    Vectors, COMPRESS, DECODE_WINDOW, LEAD_BITS, LEAD_MASKS, MARKERS, MIN_CODE_POINTS,
+    PAYLOAD_MASKS, RIGHT_ALIGN,
+};
+use crate::block::{BLOCK_BYTES, BLOCK_SYMBOLS};
+#[cfg(not(feature = "compact-tables"))]
+use crate::lookup_table::{
+    DECODE_BLOCKS, DECODE_PAGE_BASES, DECODE_PAGE_BLOCKS, ENCODE_BLOCKS, ENCODE_PAGE_BASES,
+    ENCODE_PAGE_BLOCKS, INVALID, PAGE_BITS,
+};
+#[cfg(not(feature = "compact-tables"))]
+use crate::PAGE_MASK;
+
+/// SSE4.1, with the lanes split over two 128 bit vectors
+struct Sse41;
+
+/// AVX2, which replaces the multiplications of `Sse41` with variable shifts
+struct Avx2;
+
+#[inline(always)]
+unsafe fn table(values: [u32; 4]) -> __m128i {
+    _mm_loadu_si128(values.as_ptr() as *const __m128i)
+}
+
+/// Look up the lanes of `index`, which must be less than 4, in a table of 4 lanes
+#[inline(always)]
+unsafe fn by_index(table: __m128i, index: __m128i) -> __m128i {
+    let bytes = _mm_add_epi32(
+        _mm_mullo_epi32(index, _mm_set1_epi32(0x0404_0404)),
+        _mm_set1_epi32(0x0302_0100),
+    );
+    _mm_shuffle_epi8(table, bytes)
+}
+
+#[inline(always)]
+unsafe fn encode_utf8_half(code_points: __m128i, out: *mut u8) -> usize {
+    let zero = _mm_setzero_si128();
+    // one less than the UTF-8 width of every code point
+    let widths = _mm_sub_epi32(
+        _mm_sub_epi32(
+            _mm_sub_epi32(zero, _mm_cmpgt_epi32(code_points, _mm_set1_epi32(0x7F))),
+            _mm_cmpgt_epi32(code_points, _mm_set1_epi32(0x7FF)),
+        ),
+        _mm_cmpgt_epi32(code_points, _mm_set1_epi32(0xFFFF)),
+    );
+    let payload = _mm_or_si128(
+        _mm_or_si128(
+            _mm_and_si128(code_points, _mm_set1_epi32(0x3F)),
+            _mm_and_si128(_mm_slli_epi32::<2>(code_points), _mm_set1_epi32(0x3F00)),
+        ),
+        _mm_or_si128(
+            _mm_and_si128(_mm_slli_epi32::<4>(code_points), _mm_set1_epi32(0x3F_0000)),
+            _mm_and_si128(
+                _mm_slli_epi32::<6>(code_points),
+                _mm_set1_epi32(0x3F00_0000),
+            ),
+        ),
+    );
+    let utf8 = _mm_or_si128(payload, by_index(table(MARKERS), widths));
+    // ASCII does not fit the 6 bit payloads of the longer widths
+    let utf8 = _mm_blendv_epi8(utf8, code_points, _mm_cmpeq_epi32(widths, zero));
+    let mut lanes = [0u32; 4];
+    _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, widths);
+    let index = lanes[0] | lanes[1] << 2 | lanes[2] << 4 | lanes[3] << 6;
+    let shuffle = _mm_loadu_si128(COMPRESS[index as usize].as_ptr() as *const __m128i);
+    _mm_storeu_si128(out as *mut __m128i, _mm_shuffle_epi8(utf8, shuffle));
+    (lanes.iter().sum::<u32>() + 4) as usize
+}
+
+/// The code points of half a block of chars, with a mask of the lanes which are valid UTF-8
+#[inline(always)]
+unsafe fn decode_utf8_half(words: __m128i, widths: __m128i) -> (__m128i, __m128i) {
+    let zero = _mm_setzero_si128();
+    let widths = _mm_sub_epi32(widths, _mm_set1_epi32(1));
+    let lanes = _mm_setr_epi8(0, 0, 0, 0, 4, 4, 4, 4, 8, 8, 8, 8, 12, 12, 12, 12);
+    let aligned = _mm_shuffle_epi8(
+        words,
+        _mm_add_epi8(by_index(table(RIGHT_ALIGN), widths), lanes),
+    );
+    let lead = _mm_srli_epi32::<24>(words);
+    let lead_ok = _mm_cmpeq_epi32(
+        _mm_and_si128(lead, by_index(table(LEAD_MASKS), widths)),
+        by_index(table(LEAD_BITS), widths),
+    );
+    let payload = _mm_and_si128(aligned, by_index(table(PAYLOAD_MASKS), widths));
+    let code_points = _mm_or_si128(
+        _mm_or_si128(
+            _mm_and_si128(payload, _mm_set1_epi32(0x3F)),
+            _mm_and_si128(_mm_srli_epi32::<2>(payload), _mm_set1_epi32(0xFC0)),
+        ),
+        _mm_or_si128(
+            _mm_and_si128(_mm_srli_epi32::<4>(payload), _mm_set1_epi32(0x3_F000)),
+            _mm_and_si128(_mm_srli_epi32::<6>(payload), _mm_set1_epi32(0x1C_0000)),
+        ),
+    );
+    let code_points = _mm_blendv_epi8(code_points, payload, _mm_cmpeq_epi32(widths, zero));
+    let min = _mm_sub_epi32(by_index(table(MIN_CODE_POINTS), widths), _mm_set1_epi32(1));
+    let valid = _mm_and_si128(
+        lead_ok,
+        _mm_and_si128(
+            _mm_cmpgt_epi32(code_points, min),
+            _mm_cmplt_epi32(code_points, _mm_set1_epi32(0x11_0000)),
+        ),
+    );
+    let surrogate = _mm_and_si128(
+        _mm_cmpgt_epi32(code_points, _mm_set1_epi32(0xD7FF)),
+        _mm_cmplt_epi32(code_points, _mm_set1_epi32(0xE000)),
+    );
+    (code_points, _mm_andnot_si128(surrogate, valid))
+}
+
+#[inline(always)]
+unsafe fn char_starts_half(bytes: *const u8) -> u32 {
+    // continuation bytes are 0x80 to 0xBF, the smallest signed bytes
+    let starts = _mm_cmpgt_epi8(
+        _mm_loadu_si128(bytes as *const __m128i),
+        _mm_set1_epi8(0xBFu8 as i8),
+    );
+    _mm_movemask_epi8(starts) as u32
+}
+
+/// The entries of a table at the lanes of `index`, which must be in bounds. SSE4.1 has no
+/// gathers, so the entries are loaded one by one.
+#[cfg(not(feature = "compact-tables"))]
+#[inline(always)]
+unsafe fn gather_sse41<T: Copy + Into<u32>>(table: &[T], index: __m128i) -> __m128i {
+    let entry = |idx: i32| table[idx as usize].into() as i32;
+    _mm_setr_epi32(
+        entry(_mm_extract_epi32::<0>(index)),
+        entry(_mm_extract_epi32::<1>(index)),
+        entry(_mm_extract_epi32::<2>(index)),
+        entry(_mm_extract_epi32::<3>(index)),
+    )
+}
+
+/// The code points of half a block of symbol values
+#[cfg(not(feature = "compact-tables"))]
+#[inline(always)]
+unsafe fn encode_lookup_half(values: __m128i) -> __m128i {
+    let pages = _mm_srli_epi32::<{ PAGE_BITS as i32 }>(values);
+    let blocks = gather_sse41(ENCODE_PAGE_BLOCKS, pages);
+    let offsets = _mm_or_si128(
+        _mm_slli_epi32::<{ PAGE_BITS as i32 }>(blocks),
+        _mm_and_si128(values, _mm_set1_epi32(PAGE_MASK as i32)),
+    );
+    _mm_add_epi32(
+        gather_sse41(ENCODE_PAGE_BASES, pages),
+        gather_sse41(ENCODE_BLOCKS.as_flattened(), offsets),
+    )
+}
+
+/// The symbol values of half a block of code points, with a mask of the lanes which are part of
+/// the encoding
+#[cfg(not(feature = "compact-tables"))]
+#[inline(always)]
+unsafe fn decode_lookup_half(code_points: __m128i) -> (__m128i, __m128i) {
+    let pages = _mm_srli_epi32::<{ PAGE_BITS as i32 }>(code_points);
+    // code points past the last page are looked up in it and then rejected
+    let clamped = _mm_min_epu32(pages, _mm_set1_epi32(DECODE_PAGE_BLOCKS.len() as i32 - 1));
+    let blocks = gather_sse41(DECODE_PAGE_BLOCKS, clamped);
+    let offsets = _mm_or_si128(
+        _mm_slli_epi32::<{ PAGE_BITS as i32 }>(blocks),
+        _mm_and_si128(code_points, _mm_set1_epi32(PAGE_MASK as i32)),
+    );
+    let deltas = gather_sse41(DECODE_BLOCKS.as_flattened(), offsets);
+    let valid = _mm_andnot_si128(
+        _mm_cmpeq_epi32(deltas, _mm_set1_epi32(INVALID as i32)),
+        _mm_cmpeq_epi32(pages, clamped),
+    );
+    (
+        _mm_add_epi32(gather_sse41(DECODE_PAGE_BASES, clamped), deltas),
+        valid,
+    )
+}
+
+/// The `u16` entries of a table of at least 2 entries at the lanes of `index`, which must be in
+/// bounds. Gathers load 32 bits, so the pair of entries starting at the index is loaded, or the
+/// pair ending at it for the last entry, to not read past the table.
+#[cfg(not(feature = "compact-tables"))]
+#[inline(always)]
+unsafe fn gather_u16_avx2(table: &[u16], index: __m256i) -> __m256i {
+    let pairs = _mm256_min_epu32(index, _mm256_set1_epi32(table.len() as i32 - 2));
+    let entries = _mm256_i32gather_epi32::<2>(table.as_ptr() as *const i32, pairs);
+    let shifts = _mm256_slli_epi32::<4>(_mm256_sub_epi32(index, pairs));
+    _mm256_and_si256(
+        _mm256_srlv_epi32(entries, shifts),
+        _mm256_set1_epi32(0xFFFF),
+    )
+}
+
+/// The `u32` entries of a table at the lanes of `index`, which must be in bounds
+#[cfg(not(feature = "compact-tables"))]
+#[inline(always)]
+unsafe fn gather_u32_avx2(table: &[u32], index: __m256i) -> __m256i {
+    _mm256_i32gather_epi32::<4>(table.as_ptr() as *const i32, index)
+}
+
+/// Join the symbols of a block, each shifted to the 3 bytes from byte `2 * i` of the block, into
+/// bytes
+#[inline(always)]
+unsafe fn pack_words(lo: __m128i, hi: __m128i) -> [u8; BLOCK_BYTES] {
+    // the byte where one symbol ends and the next starts is shared between their lanes
+    let bytes = _mm_or_si128(
+        _mm_or_si128(
+            _mm_shuffle_epi8(
+                lo,
+                _mm_setr_epi8(2, 1, 6, 5, 10, 9, 14, 13, -1, -1, -1, -1, -1, -1, -1, -1),
+            ),
+            _mm_shuffle_epi8(
+                lo,
+                _mm_setr_epi8(-1, -1, 0, -1, 4, -1, 8, -1, 12, -1, -1, -1, -1, -1, -1, -1),
+            ),
+        ),
+        _mm_or_si128(
+            _mm_shuffle_epi8(
+                hi,
+                _mm_setr_epi8(-1, -1, -1, -1, -1, -1, -1, -1, 2, 1, 6, 5, 10, 9, 14, 13),
+            ),
+            _mm_shuffle_epi8(
+                hi,
+                _mm_setr_epi8(-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 0, -1, 4, -1, 8, -1),
+            ),
+        ),
+    );
+    let mut block = [0; BLOCK_BYTES];
+    _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, bytes);
+    block[16] = _mm_extract_epi8::<12>(hi) as u8;
+    block
+}
+
+impl Vectors for Sse41 {
+    type Lanes = (__m128i, __m128i);
+
+    #[inline(always)]
+    unsafe fn load(lanes: &[u32; BLOCK_SYMBOLS]) -> Self::Lanes {
+        // inserting the lanes avoids loading the vectors from lanes which were just stored one by
+        // one, which stalls store forwarding
+        let lanes = lanes.map(|lane| lane as i32);
+        (
+            _mm_setr_epi32(lanes[0], lanes[1], lanes[2], lanes[3]),
+            _mm_setr_epi32(lanes[4], lanes[5], lanes[6], lanes[7]),
+        )
+    }
+
+    #[cfg(feature = "compact-tables")]
+    #[inline(always)]
+    unsafe fn store(lanes: Self::Lanes) -> [u32; BLOCK_SYMBOLS] {
+        let mut out = [0; BLOCK_SYMBOLS];
+        let ptr = out.as_mut_ptr() as *mut __m128i;
+        _mm_storeu_si128(ptr, lanes.0);
+        _mm_storeu_si128(ptr.add(1), lanes.1);
+        out
+    }
+
+    #[inline(always)]
+    unsafe fn unpack(block: &[u8; BLOCK_BYTES]) -> Self::Lanes {
+        let ptr = block.as_ptr();
+        // symbol `i` lies within the 3 bytes from byte `2 * i`, the second half is loaded from the
+        // second byte to reach the last byte of the block
+        let lo = _mm_shuffle_epi8(
+            _mm_loadu_si128(ptr as *const __m128i),
+            _mm_setr_epi8(2, 1, 0, -1, 4, 3, 2, -1, 6, 5, 4, -1, 8, 7, 6, -1),
+        );
+        let hi = _mm_shuffle_epi8(
+            _mm_loadu_si128(ptr.add(1) as *const __m128i),
+            _mm_setr_epi8(9, 8, 7, -1, 11, 10, 9, -1, 13, 12, 11, -1, 15, 14, 13, -1),
+        );
+        // symbol `i` starts `i` bits into its bytes. Without variable shifts it is shifted left
+        // by `i` with a multiplication, and then right by 7.
+        let mask = _mm_set1_epi32(0x1FFFF);
+        (
+            _mm_and_si128(
+                _mm_srli_epi32::<7>(_mm_mullo_epi32(lo, _mm_setr_epi32(1, 2, 4, 8))),
+                mask,
+            ),
+            _mm_and_si128(
+                _mm_srli_epi32::<7>(_mm_mullo_epi32(hi, _mm_setr_epi32(16, 32, 64, 128))),
+                mask,
+            ),
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn pack(values: Self::Lanes) -> [u8; BLOCK_BYTES] {
+        pack_words(
+            _mm_mullo_epi32(values.0, _mm_setr_epi32(128, 64, 32, 16)),
+            _mm_mullo_epi32(values.1, _mm_setr_epi32(8, 4, 2, 1)),
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn encode_utf8(code_points: Self::Lanes, out: *mut u8) -> usize {
+        let len = encode_utf8_half(code_points.0, out);
+        len + encode_utf8_half(code_points.1, out.add(len))
+    }
+
+    #[inline(always)]
+    unsafe fn char_starts(window: &[u8; DECODE_WINDOW]) -> u32 {
+        char_starts_half(window.as_ptr()) | char_starts_half(window.as_ptr().add(16)) << 16
+    }
+
+    #[inline(always)]
+    unsafe fn decode_utf8(words: Self::Lanes, widths: Self::Lanes) -> Option<Self::Lanes> {
+        let (lo, lo_valid) = decode_utf8_half(words.0, widths.0);
+        let (hi, hi_valid) = decode_utf8_half(words.1, widths.1);
+        if _mm_movemask_epi8(_mm_and_si128(lo_valid, hi_valid)) == 0xFFFF {
+            Some((lo, hi))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(feature = "compact-tables"))]
+    #[inline(always)]
+    unsafe fn encode_lookup(values: Self::Lanes) -> Self::Lanes {
+        (encode_lookup_half(values.0), encode_lookup_half(values.1))
+    }
+
+    #[cfg(not(feature = "compact-tables"))]
+    #[inline(always)]
+    unsafe fn decode_lookup(code_points: Self::Lanes) -> Option<Self::Lanes> {
+        let (lo, lo_valid) = decode_lookup_half(code_points.0);
+        let (hi, hi_valid) = decode_lookup_half(code_points.1);
+        if _mm_movemask_epi8(_mm_and_si128(lo_valid, hi_valid)) == 0xFFFF {
+            Some((lo, hi))
+        } else {
+            None
+        }
+    }
+}
+
+impl Vectors for Avx2 {
+    type Lanes = (__m128i, __m128i);
+
+    #[inline(always)]
+    unsafe fn load(lanes: &[u32; BLOCK_SYMBOLS]) -> Self::Lanes {
+        Sse41::load(lanes)
+    }
+
+    #[cfg(feature = "compact-tables")]
+    #[inline(always)]
+    unsafe fn store(lanes: Self::Lanes) -> [u32; BLOCK_SYMBOLS] {
+        Sse41::store(lanes)
+    }
+
+    #[inline(always)]
+    unsafe fn unpack(block: &[u8; BLOCK_BYTES]) -> Self::Lanes {
+        let ptr = block.as_ptr();
+        let bytes = _mm256_loadu2_m128i(ptr.add(1) as *const __m128i, ptr as *const __m128i);
+        let words = _mm256_shuffle_epi8(
+            bytes,
+            _mm256_setr_epi8(
+                2, 1, 0, -1, 4, 3, 2, -1, 6, 5, 4, -1, 8, 7, 6, -1, 9, 8, 7, -1, 11, 10, 9, -1, 13,
+                12, 11, -1, 15, 14, 13, -1,
+            ),
+        );
+        let values = _mm256_and_si256(
+            _mm256_srlv_epi32(words, _mm256_setr_epi32(7, 6, 5, 4, 3, 2, 1, 0)),
+            _mm256_set1_epi32(0x1FFFF),
+        );
+        (
+            _mm256_castsi256_si128(values),
+            _mm256_extracti128_si256::<1>(values),
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn pack(values: Self::Lanes) -> [u8; BLOCK_BYTES] {
+        let words = _mm256_sllv_epi32(
+            _mm256_set_m128i(values.1, values.0),
+            _mm256_setr_epi32(7, 6, 5, 4, 3, 2, 1, 0),
+        );
+        pack_words(
+            _mm256_castsi256_si128(words),
+            _mm256_extracti128_si256::<1>(words),
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn encode_utf8(code_points: Self::Lanes, out: *mut u8) -> usize {
+        Sse41::encode_utf8(code_points, out)
+    }
+
+    #[inline(always)]
+    unsafe fn char_starts(window: &[u8; DECODE_WINDOW]) -> u32 {
+        let starts = _mm256_cmpgt_epi8(
+            _mm256_loadu_si256(window.as_ptr() as *const __m256i),
+            _mm256_set1_epi8(0xBFu8 as i8),
+        );
+        _mm256_movemask_epi8(starts) as u32
+    }
+
+    #[inline(always)]
+    unsafe fn decode_utf8(words: Self::Lanes, widths: Self::Lanes) -> Option<Self::Lanes> {
+        Sse41::decode_utf8(words, widths)
+    }
+
+    #[cfg(not(feature = "compact-tables"))]
+    #[inline(always)]
+    unsafe fn encode_lookup(values: Self::Lanes) -> Self::Lanes {
+        let values = _mm256_set_m128i(values.1, values.0);
+        let pages = _mm256_srli_epi32::<{ PAGE_BITS as i32 }>(values);
+        let blocks = gather_u16_avx2(ENCODE_PAGE_BLOCKS, pages);
+        let offsets = _mm256_or_si256(
+            _mm256_slli_epi32::<{ PAGE_BITS as i32 }>(blocks),
+            _mm256_and_si256(values, _mm256_set1_epi32(PAGE_MASK as i32)),
+        );
+        let code_points = _mm256_add_epi32(
+            gather_u32_avx2(ENCODE_PAGE_BASES, pages),
+            gather_u16_avx2(ENCODE_BLOCKS.as_flattened(), offsets),
+        );
+        (
+            _mm256_castsi256_si128(code_points),
+            _mm256_extracti128_si256::<1>(code_points),
+        )
+    }
+
+    #[cfg(not(feature = "compact-tables"))]
+    #[inline(always)]
+    unsafe fn decode_lookup(code_points: Self::Lanes) -> Option<Self::Lanes> {
+        let code_points = _mm256_set_m128i(code_points.1, code_points.0);
+        let pages = _mm256_srli_epi32::<{ PAGE_BITS as i32 }>(code_points);
+        // code points past the last page are looked up in it and then rejected
+        let clamped = _mm256_min_epu32(
+            pages,
+            _mm256_set1_epi32(DECODE_PAGE_BLOCKS.len() as i32 - 1),
+        );
+        let blocks = gather_u16_avx2(DECODE_PAGE_BLOCKS, clamped);
+        let offsets = _mm256_or_si256(
+            _mm256_slli_epi32::<{ PAGE_BITS as i32 }>(blocks),
+            _mm256_and_si256(code_points, _mm256_set1_epi32(PAGE_MASK as i32)),
+        );
+        let deltas = gather_u16_avx2(DECODE_BLOCKS.as_flattened(), offsets);
+        let valid = _mm256_andnot_si256(
+            _mm256_cmpeq_epi32(deltas, _mm256_set1_epi32(INVALID as i32)),
+            _mm256_cmpeq_epi32(pages, clamped),
+        );
+        if _mm256_movemask_epi8(valid) != -1 {
+            return None;
+        }
+        let values = _mm256_add_epi32(gather_u32_avx2(DECODE_PAGE_BASES, clamped), deltas);
+        Some((
+            _mm256_castsi256_si128(values),
+            _mm256_extracti128_si256::<1>(values),
+        ))
+    }
+}
+
+#[target_feature(enable = "sse4.1")]
+pub(super) unsafe fn encode_blocks_sse41(input: &[u8], out: &mut Vec<u8>) -> usize {
+    super::encode_blocks_with::<Sse41>(input, out)
+}
+
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn encode_blocks_avx2(input: &[u8], out: &mut Vec<u8>) -> usize {
+    super::encode_blocks_with::<Avx2>(input, out)
+}
+
+#[target_feature(enable = "sse4.1")]
+pub(super) unsafe fn decode_blocks_sse41(input: &[u8], out: &mut Vec<u8>) -> (usize, usize) {
+    super::decode_blocks_with::<Sse41>(input, out)
+}
+
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn decode_blocks_avx2(input: &[u8], out: &mut Vec<u8>) -> (usize, usize) {
+    super::decode_blocks_with::<Avx2>(input, out)
+}
@@ -0,0 +1,374 @@
+//! Incremental encoding and decoding of input which arrives in pieces, producing the same output
+//! as the one-shot functions
+
+#[cfg(all(feature = "serde", not(feature = "std")))]
+use alloc::vec::Vec;
+#[cfg(any(feature = "std", feature = "bytes"))]
+use core::str;
+
+use crate::base_n::BnToB8Iter;
+use crate::block::{decode_block, BLOCK_BYTES, BLOCK_SYMBOLS};
+#[cfg(feature = "bytes")]
+use crate::encode_chars;
+#[cfg(any(feature = "std", feature = "serde"))]
+use crate::encode_utf8_into;
+use crate::lookup_table::{PAD1, PAD2};
+#[cfg(any(feature = "std", feature = "bytes"))]
+use crate::DecodeError;
+use crate::{Block, InvalidChar, B17};
+
+/// Encodes bytes fed in pieces, holding back the bytes of an incomplete block
+#[cfg(any(feature = "std", feature = "serde", feature = "bytes"))]
+pub(crate) struct Encoder {
+    tail: [u8; BLOCK_BYTES],
+    len: usize,
+}
+
+#[cfg(any(feature = "std", feature = "serde", feature = "bytes"))]
+impl Encoder {
+    pub(crate) const fn new() -> Self {
+        Self {
+            tail: [0; BLOCK_BYTES],
+            len: 0,
+        }
+    }
+
+    /// Pass the whole blocks completed by `input` to `encode`, which are never padded
+    fn feed(&mut self, mut input: &[u8], mut encode: impl FnMut(&[u8])) {
+        if self.len > 0 {
+            let take = (BLOCK_BYTES - self.len).min(input.len());
+            self.tail[self.len..self.len + take].copy_from_slice(&input[..take]);
+            self.len += take;
+            input = &input[take..];
+            if self.len < BLOCK_BYTES {
+                return;
+            }
+            encode(&self.tail);
+            self.len = 0;
+        }
+        let whole = input.len() - input.len() % BLOCK_BYTES;
+        encode(&input[..whole]);
+        self.len = input.len() - whole;
+        self.tail[..self.len].copy_from_slice(&input[whole..]);
+    }
+
+    /// Append the UTF-8 encoding of every block completed by `input` to `out`
+    #[cfg(any(feature = "std", feature = "serde"))]
+    pub(crate) fn update(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        self.feed(input, |blocks| encode_utf8_into(blocks, out));
+    }
+
+    /// Append the UTF-8 encoding of the held back bytes and the padding to `out`, leaving the
+    /// encoder ready for a new input
+    #[cfg(any(feature = "std", feature = "serde"))]
+    pub(crate) fn finish(&mut self, out: &mut Vec<u8>) {
+        encode_utf8_into(&self.tail[..self.len], out);
+        self.len = 0;
+    }
+
+    /// Pass the chars of every block completed by `input` to `f`
+    #[cfg(feature = "bytes")]
+    pub(crate) fn update_chars(&mut self, input: &[u8], mut f: impl FnMut(char)) {
+        self.feed(input, |blocks| encode_chars(blocks).for_each(&mut f));
+    }
+
+    /// Pass the chars of the held back bytes and the padding to `f`, leaving the encoder ready
+    /// for a new input
+    #[cfg(feature = "bytes")]
+    pub(crate) fn finish_chars(&mut self, f: impl FnMut(char)) {
+        encode_chars(&self.tail[..self.len]).for_each(f);
+        self.len = 0;
+    }
+}
+
+#[cfg(any(feature = "std", feature = "serde", feature = "bytes"))]
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.tail);
+    }
+}
+
+/// Decodes chars fed one by one
+///
+/// A complete block is only released once the next symbol arrives, as padding at the end of the
+/// input may still remove some of its bytes.
+pub(crate) struct Decoder {
+    block: Block,
+    filled: usize,
+    chars: usize,
+    padding: Option<(usize, char)>,
+}
+
+impl Decoder {
+    pub(crate) const fn new() -> Self {
+        Self {
+            block: Block([0; BLOCK_SYMBOLS]),
+            filled: 0,
+            chars: 0,
+            padding: None,
+        }
+    }
+
+    /// Decode the next char, returning the bytes of the previous block if this completes it
+    pub(crate) fn push(&mut self, ch: char) -> Result<Option<[u8; BLOCK_BYTES]>, InvalidChar> {
+        let idx = self.chars;
+        self.chars += 1;
+        if let Some((pad_idx, pad)) = self.padding {
+            return Err(InvalidChar(pad_idx, pad));
+        }
+        if matches!(ch as u32, PAD1 | PAD2) {
+            self.padding = Some((idx, ch));
+            return Ok(None);
+        }
+        let value = B17::decode(ch).ok_or(InvalidChar(idx, ch))?.0;
+        let full = (self.filled == BLOCK_SYMBOLS).then(|| {
+            self.filled = 0;
+            decode_block(&self.block.0)
+        });
+        self.block.0[self.filled] = value;
+        self.filled += 1;
+        Ok(full)
+    }
+
+    /// Decode the symbols held back, returning the last bytes of the input with the padding
+    /// removed and how many there are
+    pub(crate) fn finish(&self) -> Result<([u8; BLOCK_BYTES], usize), InvalidChar> {
+        let mut bytes = [0; BLOCK_BYTES];
+        let mut len = 0;
+        for byte in BnToB8Iter::<_, 17>::new(self.block.0[..self.filled].iter().copied()) {
+            bytes[len] = byte;
+            len += 1;
+        }
+        if let Some((pad_idx, pad)) = self.padding {
+            let padding = if pad as u32 == PAD1 { 1 } else { 2 };
+            // only an input without symbols has fewer bytes held back than padding
+            if padding > len {
+                return Err(InvalidChar(pad_idx, pad));
+            }
+            len -= padding;
+        }
+        Ok((bytes, len))
+    }
+}
+
+/// Decodes UTF-8 fed in chunks, which may split chars between them
+#[cfg(any(feature = "std", feature = "bytes"))]
+pub(crate) struct Utf8Decoder {
+    decoder: Decoder,
+    /// The start of a char split between chunks
+    pending: [u8; 4],
+    pending_len: usize,
+    /// The offset of the first byte which is not decoded yet
+    offset: usize,
+}
+
+#[cfg(any(feature = "std", feature = "bytes"))]
+impl Utf8Decoder {
+    pub(crate) const fn new() -> Self {
+        Self {
+            decoder: Decoder::new(),
+            pending: [0; 4],
+            pending_len: 0,
+            offset: 0,
+        }
+    }
+
+    /// Decode the next chunk, passing the bytes of every block it completes to `f`
+    ///
+    /// Fails with the same error as [`decode_utf8_bytes`](crate::decode_utf8_bytes), after
+    /// passing the bytes decoded before it to `f`.
+    pub(crate) fn update(
+        &mut self,
+        mut chunk: &[u8],
+        mut f: impl FnMut(&[u8]),
+    ) -> Result<(), DecodeError> {
+        if self.pending_len > 0 {
+            let take = (self.pending.len() - self.pending_len).min(chunk.len());
+            self.pending[self.pending_len..self.pending_len + take].copy_from_slice(&chunk[..take]);
+            let valid = match str::from_utf8(&self.pending[..self.pending_len + take]) {
+                Ok(valid) => valid,
+                Err(err) if err.valid_up_to() > 0 => {
+                    str::from_utf8(&self.pending[..err.valid_up_to()]).unwrap()
+                }
+                Err(err) if err.error_len().is_some() => {
+                    return Err(DecodeError::InvalidUtf8 {
+                        offset: self.offset,
+                    });
+                }
+                // the chunk ends before the char does
+                Err(_) => {
+                    self.pending_len += take;
+                    return Ok(());
+                }
+            };
+            let ch = valid.chars().next().unwrap();
+            self.push(ch, &mut f)?;
+            chunk = &chunk[ch.len_utf8() - self.pending_len..];
+            self.offset += ch.len_utf8();
+        }
+        let (valid, invalid) = match str::from_utf8(chunk) {
+            Ok(valid) => (valid, false),
+            Err(err) => (
+                str::from_utf8(&chunk[..err.valid_up_to()]).unwrap(),
+                err.error_len().is_some(),
+            ),
+        };
+        for ch in valid.chars() {
+            self.push(ch, &mut f)?;
+        }
+        self.offset += valid.len();
+        if invalid {
+            return Err(DecodeError::InvalidUtf8 {
+                offset: self.offset,
+            });
+        }
+        // otherwise only the start of a char which continues in the next chunk is left
+        let rest = &chunk[valid.len()..];
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_len = rest.len();
+        Ok(())
+    }
+
+    /// Decode the symbols held back, passing the last bytes of the input to `f`
+    pub(crate) fn finish(&self, f: impl FnOnce(&[u8])) -> Result<(), DecodeError> {
+        if self.pending_len > 0 {
+            return Err(DecodeError::InvalidUtf8 {
+                offset: self.offset,
+            });
+        }
+        let (tail, len) = self.decoder.finish()?;
+        f(&tail[..len]);
+        Ok(())
+    }
+
+    fn push(&mut self, ch: char, f: &mut impl FnMut(&[u8])) -> Result<(), InvalidChar> {
+        if let Some(block) = self.decoder.push(ch)? {
+            f(&block);
+        }
+        Ok(())
+    }
+}
+
+/// Reads the decoded bytes of chars one at a time
+pub(crate) struct ByteReader<I> {
+    chars: I,
+    decoder: Decoder,
+    /// The decoded bytes not yet read are `block[pos..len]`
+    block: [u8; BLOCK_BYTES],
+    pos: usize,
+    len: usize,
+    finished: bool,
+}
+
+impl<I: Iterator<Item = char>> ByteReader<I> {
+    pub(crate) const fn new(chars: I) -> Self {
+        Self {
+            chars,
+            decoder: Decoder::new(),
+            block: [0; BLOCK_BYTES],
+            pos: 0,
+            len: 0,
+            finished: false,
+        }
+    }
+
+    pub(crate) fn next_byte(&mut self) -> Result<Option<u8>, InvalidChar> {
+        while self.pos == self.len {
+            if self.finished {
+                return Ok(None);
+            }
+            self.pos = 0;
+            self.len = 0;
+            match self.chars.next() {
+                Some(ch) => {
+                    if let Some(block) = self.decoder.push(ch)? {
+                        self.block = block;
+                        self.len = BLOCK_BYTES;
+                    }
+                }
+                None => {
+                    (self.block, self.len) = self.decoder.finish()?;
+                    self.finished = true;
+                }
+            }
+        }
+        self.pos += 1;
+        Ok(Some(self.block[self.pos - 1]))
+    }
+}
+
+impl<I> Drop for ByteReader<I> {
+    fn drop(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::tests::random;
+    use crate::test_support::corrupted;
+    use crate::{decode, encode};
+
+    fn random_bytes(state: &mut u64, len: usize) -> Vec<u8> {
+        (0..len).map(|_| random(state) as u8).collect()
+    }
+
+    fn decode_stream(string: &str) -> Result<Vec<u8>, InvalidChar> {
+        let mut decoder = Decoder::new();
+        let mut out = Vec::new();
+        for ch in string.chars() {
+            if let Some(block) = decoder.push(ch)? {
+                out.extend_from_slice(&block);
+            }
+        }
+        let (tail, len) = decoder.finish()?;
+        out.extend_from_slice(&tail[..len]);
+        Ok(out)
+    }
+
+    fn read_bytes(string: &str) -> Result<Vec<u8>, InvalidChar> {
+        let mut reader = ByteReader::new(string.chars());
+        let mut out = Vec::new();
+        while let Some(byte) = reader.next_byte()? {
+            out.push(byte);
+        }
+        Ok(out)
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn encoding_matches_oneshot() {
+        let mut state = 0x6A09_E667_F3BC_C908;
+        let mut encoder = Encoder::new();
+        for len in 0..200 {
+            let input = random_bytes(&mut state, len);
+            let mut out = Vec::new();
+            let mut rest = &input[..];
+            while !rest.is_empty() {
+                let (piece, next) =
+                    rest.split_at(random(&mut state) as usize % 40 % (rest.len() + 1));
+                encoder.update(piece, &mut out);
+                rest = next;
+            }
+            encoder.finish(&mut out);
+            assert_eq!(String::from_utf8(out).unwrap(), encode(&input));
+        }
+    }
+
+    #[test]
+    fn decoding_matches_oneshot() {
+        let mut state = 0xBB67_AE85_84CA_A73B;
+        for len in 0..100 {
+            let encoded = encode(random_bytes(&mut state, len));
+            assert_eq!(decode_stream(&encoded), decode(&encoded));
+            assert_eq!(read_bytes(&encoded), decode(&encoded));
+            for corrupted in corrupted(&mut state, &encoded) {
+                assert_eq!(decode_stream(&corrupted), decode(&corrupted));
+                assert_eq!(read_bytes(&corrupted), decode(&corrupted));
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Checks of the generated tables evaluated at compile time, which the unchecked char conversions
+//! rely on for their soundness
+
+#[cfg(feature = "compact-tables")]
+use crate::lookup_table::{CHECKPOINTS, CHECKPOINT_INTERVAL, COMPACT_RANGES};
+#[cfg(not(feature = "compact-tables"))]
+use crate::lookup_table::{
+    DECODE_BLOCKS, DECODE_PAGE_BASES, DECODE_PAGE_BLOCKS, ENCODE_BLOCKS, ENCODE_PAGE_BASES,
+    ENCODE_PAGE_BLOCKS, INVALID, PAGE_BITS,
+};
+use crate::lookup_table::{LOOKUP_TABLE, PAD1, PAD2};
+#[cfg(not(feature = "compact-tables"))]
+use crate::PAGE_MASK;
+
+const SYMBOLS: u32 = 1 << 17;
+
+const fn in_ranges(code_point: u32) -> bool {
+    let mut idx = 0;
+    while idx < LOOKUP_TABLE.len() {
+        let (_, start, end) = LOOKUP_TABLE[idx];
+        if start <= code_point && code_point <= end {
+            return true;
+        }
+        idx += 1;
+    }
+    false
+}
+
+// The ranges are sorted, contiguous and cover exactly the symbol values, without surrogates or
+// code points beyond U+10FFFF
+const _: () = {
+    let mut idx = 0;
+    let mut next_offset = 0;
+    while idx < LOOKUP_TABLE.len() {
+        let (offset, start, end) = LOOKUP_TABLE[idx];
+        assert!(offset == next_offset, "range offsets are not contiguous");
+        assert!(start <= end, "range is empty");
+        assert!(
+            idx == 0 || start > LOOKUP_TABLE[idx - 1].2,
+            "ranges are not sorted"
+        );
+        assert!(end <= char::MAX as u32, "range exceeds U+10FFFF");
+        assert!(end < 0xD800 || start > 0xDFFF, "range contains surrogates");
+        next_offset += end - start + 1;
+        idx += 1;
+    }
+    assert!(next_offset == SYMBOLS, "ranges do not total 2^17 symbols");
+};
+
+// The padding chars are valid and distinct from the symbols and each other
+const _: () = {
+    assert!(char::from_u32(PAD1).is_some(), "PAD1 is not a char");
+    assert!(char::from_u32(PAD2).is_some(), "PAD2 is not a char");
+    assert!(PAD1 != PAD2, "padding chars are the same");
+    assert!(!in_ranges(PAD1), "PAD1 is a symbol");
+    assert!(!in_ranges(PAD2), "PAD2 is a symbol");
+};
+
+// The page tables map every symbol value to the code point given by the ranges and back, and no
+// other code point to a symbol value
+#[cfg(not(feature = "compact-tables"))]
+const _: () = {
+    assert!(
+        ENCODE_PAGE_BASES.len() == ENCODE_PAGE_BLOCKS.len()
+            && ENCODE_PAGE_BASES.len() << PAGE_BITS == SYMBOLS as usize,
+        "encode pages do not cover the symbol values"
+    );
+    assert!(
+        DECODE_PAGE_BASES.len() == DECODE_PAGE_BLOCKS.len(),
+        "decode pages are inconsistent"
+    );
+    let mut idx = 0;
+    while idx < LOOKUP_TABLE.len() {
+        let (offset, start, end) = LOOKUP_TABLE[idx];
+        let mut value = offset;
+        while value <= offset + (end - start) {
+            let page = (value >> PAGE_BITS) as usize;
+            let delta =
+                ENCODE_BLOCKS[ENCODE_PAGE_BLOCKS[page] as usize][(value & PAGE_MASK) as usize];
+            let code_point = ENCODE_PAGE_BASES[page] + delta as u32;
+            assert!(
+                code_point == start + (value - offset),
+                "encode pages disagree with ranges"
+            );
+            let page = (code_point >> PAGE_BITS) as usize;
+            let delta =
+                DECODE_BLOCKS[DECODE_PAGE_BLOCKS[page] as usize][(code_point & PAGE_MASK) as usize];
+            assert!(
+                delta != INVALID && DECODE_PAGE_BASES[page] + delta as u32 == value,
+                "decode pages disagree with ranges"
+            );
+            value += 1;
+        }
+        idx += 1;
+    }
+    let mut valid = 0;
+    let mut page = 0;
+    while page < DECODE_PAGE_BLOCKS.len() {
+        let block = &DECODE_BLOCKS[DECODE_PAGE_BLOCKS[page] as usize];
+        let mut idx = 0;
+        while idx < block.len() {
+            valid += (block[idx] != INVALID) as u32;
+            idx += 1;
+        }
+        page += 1;
+    }
+    assert!(valid == SYMBOLS, "decode pages have invalid code points");
+};
+
+// The delta encoded ranges and their checkpoints reproduce the ranges
+#[cfg(feature = "compact-tables")]
+const _: () = {
+    assert!(
+        COMPACT_RANGES.len() == LOOKUP_TABLE.len(),
+        "compact ranges do not match the ranges"
+    );
+    let mut idx = 0;
+    let mut next_start = 0;
+    while idx < COMPACT_RANGES.len() {
+        let (gap, len) = COMPACT_RANGES[idx];
+        let (offset, start, end) = LOOKUP_TABLE[idx];
+        assert!(
+            next_start + gap as u32 == start && start + len as u32 == end,
+            "compact ranges do not match the ranges"
+        );
+        if idx % CHECKPOINT_INTERVAL == 0 {
+            let (checkpoint_offset, checkpoint_start) = CHECKPOINTS[idx / CHECKPOINT_INTERVAL];
+            assert!(
+                checkpoint_offset == offset && checkpoint_start == start,
+                "checkpoints do not match the ranges"
+            );
+        }
+        next_start = end + 1;
+        idx += 1;
+    }
+    assert!(
+        CHECKPOINTS.len() == LOOKUP_TABLE.len().div_ceil(CHECKPOINT_INTERVAL),
+        "checkpoints do not match the ranges"
+    );
+};
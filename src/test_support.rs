@@ -0,0 +1,38 @@
+//! Corrupted inputs shared by the tests of the different decoders, which are checked to fail, or
+//! succeed for padding at the end, exactly like [`decode`](crate::decode)
+
+use crate::block::tests::random;
+use crate::lookup_table::{PAD1, PAD2};
+
+/// An encoded string with a padding char or a newline inserted at a random char index, once for
+/// each of them
+pub(crate) fn corrupted(state: &mut u64, encoded: &str) -> [String; 3] {
+    let at = random(state) as usize % (encoded.chars().count() + 1);
+    let at = encoded
+        .char_indices()
+        .nth(at)
+        .map_or(encoded.len(), |(idx, _)| idx);
+    [PAD1, PAD2, '\n' as u32].map(|ch| {
+        let mut corrupted = encoded.to_owned();
+        corrupted.insert(at, char::from_u32(ch).unwrap());
+        corrupted
+    })
+}
+
+/// Encoded UTF-8 with an invalid byte, a newline or a padding char inserted at a random byte index,
+/// which may split a char, and truncated there, once for each of them
+pub(crate) fn corrupted_bytes(state: &mut u64, encoded: &[u8]) -> [Vec<u8>; 4] {
+    let at = random(state) as usize % (encoded.len() + 1);
+    let mut pad = [0; 4];
+    let pad = char::from_u32(PAD1)
+        .unwrap()
+        .encode_utf8(&mut pad)
+        .as_bytes();
+    let insert = |bytes: &[u8]| [&encoded[..at], bytes, &encoded[at..]].concat();
+    [
+        insert(&[0xFF]),
+        insert(b"\n"),
+        insert(pad),
+        encoded[..at].to_vec(),
+    ]
+}
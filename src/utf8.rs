@@ -0,0 +1,155 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::DecodeError;
+
+/// Iterator over the characters of UTF-8 encoded bytes, validating them on the fly
+#[derive(Debug, Clone)]
+pub(crate) struct Utf8Chars<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Utf8Chars<'a> {
+    /// Iterate over the characters of `bytes` starting at the char boundary `offset`
+    pub(crate) fn new(bytes: &'a [u8], offset: usize) -> Self {
+        Self { bytes, offset }
+    }
+
+    fn continuation(&self, idx: usize) -> Option<u32> {
+        match self.bytes.get(self.offset + idx) {
+            Some(&byte) if byte & 0xC0 == 0x80 => Some((byte & 0x3F) as u32),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Iterator for Utf8Chars<'a> {
+    type Item = Result<char, DecodeError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = *self.bytes.get(self.offset)?;
+        let invalid = DecodeError::InvalidUtf8 {
+            offset: self.offset,
+        };
+        let (len, min, mut code_point) = match first {
+            0x00..=0x7F => (1, 0, first as u32),
+            0xC2..=0xDF => (2, 0x80, (first & 0x1F) as u32),
+            0xE0..=0xEF => (3, 0x800, (first & 0x0F) as u32),
+            0xF0..=0xF4 => (4, 0x10000, (first & 0x07) as u32),
+            _ => {
+                self.offset = self.bytes.len();
+                return Some(Err(invalid));
+            }
+        };
+        for idx in 1..len {
+            if let Some(bits) = self.continuation(idx) {
+                code_point = (code_point << 6) | bits;
+            } else {
+                self.offset = self.bytes.len();
+                return Some(Err(invalid));
+            }
+        }
+        match char::from_u32(code_point).filter(|_| code_point >= min) {
+            Some(ch) => {
+                self.offset += len;
+                Some(Ok(ch))
+            }
+            None => {
+                self.offset = self.bytes.len();
+                Some(Err(invalid))
+            }
+        }
+    }
+}
+
+/// Append the UTF-8 encoding of a valid code point to `out`. The bytes are computed and written
+/// without branching or indexing on the width, as the widths of encoded symbols are unpredictable
+/// and, for [`ct`](crate::ct), secret.
+#[inline(always)]
+pub(crate) fn push_utf8(out: &mut Vec<u8>, code_point: u32) {
+    let width = 1
+        + (code_point > 0x7F) as usize
+        + (code_point > 0x7FF) as usize
+        + (code_point > 0xFFFF) as usize;
+    let tail_bits = 8 * (width as u32 - 1);
+    // the continuation bytes in reverse order, below the lead byte
+    let payload = (code_point & 0x3F)
+        | (code_point << 2 & 0x3F00)
+        | (code_point << 4 & 0x3F_0000)
+        | (code_point << 6 & 0x3F00_0000);
+    let markers = (0xFF00 >> width & 0xFF) << tail_bits | (0x80_8080 & ((1 << tail_bits) - 1));
+    // all ones for ASCII, which does not fit the 6 bit payloads
+    let ascii = ((code_point > 0x7F) as u32).wrapping_sub(1);
+    let sequence = (code_point & ascii) | ((payload | markers) & !ascii);
+    let bytes = (sequence << (32 - 8 * width as u32)).to_be_bytes();
+    #[cfg(not(feature = "forbid-unsafe"))]
+    {
+        out.reserve(4);
+        let len = out.len();
+        // SAFETY: all 4 bytes are within the capacity, and the first `width` are initialized
+        unsafe {
+            out.as_mut_ptr()
+                .add(len)
+                .cast::<[u8; 4]>()
+                .write_unaligned(bytes);
+            out.set_len(len + width);
+        }
+    }
+    #[cfg(feature = "forbid-unsafe")]
+    out.extend_from_slice(&bytes[..width]);
+}
+
+/// The string of bytes which are known to be whole UTF-8 encoded chars
+pub(crate) fn into_string(bytes: Vec<u8>) -> String {
+    #[cfg(not(feature = "forbid-unsafe"))]
+    {
+        // SAFETY: see above
+        unsafe { String::from_utf8_unchecked(bytes) }
+    }
+    #[cfg(feature = "forbid-unsafe")]
+    {
+        String::from_utf8(bytes).expect("invalid UTF-8 written")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_std() {
+        let valid = "a\u{7F}\u{80}\u{7FF}\u{800}\u{FFFF}\u{10000}\u{10FFFF}";
+        assert_eq!(
+            Utf8Chars::new(valid.as_bytes(), 0)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            valid.chars().collect::<Vec<_>>()
+        );
+        for invalid in [
+            &b"ab\x80"[..],
+            b"ab\xC0\x80",
+            b"ab\xE0\x80\x80",
+            b"ab\xED\xA0\x80",
+            b"ab\xF4\x90\x80\x80",
+            b"ab\xF0\x90\x80",
+            b"ab\xE2\x82",
+            b"ab\xFF",
+        ] {
+            assert!(core::str::from_utf8(invalid).is_err());
+            assert_eq!(
+                Utf8Chars::new(invalid, 0).nth(2),
+                Some(Err(DecodeError::InvalidUtf8 { offset: 2 }))
+            );
+        }
+    }
+
+    #[test]
+    fn push_matches_std() {
+        let mut out = Vec::with_capacity(4);
+        for ch in (0..=0x10FFFF).filter_map(char::from_u32) {
+            out.clear();
+            push_utf8(&mut out, ch as u32);
+            assert_eq!(out, ch.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Byte containers which convert to and from base131072 strings at the edges, through `FromStr`
+//! and `Display`
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::str::FromStr;
+
+use crate::{decode, decode_to_array, encode, DecodeError, InvalidChar};
+
+/// Bytes which are parsed from and displayed as a base131072 string
+///
+/// ```
+/// use base131072::Base131072Bytes;
+///
+/// let bytes = Base131072Bytes::from(b"hello world".to_vec());
+/// let string = bytes.to_string();
+/// assert_eq!(string.parse::<Base131072Bytes>().unwrap(), bytes);
+/// assert_eq!(&bytes[..5], b"hello");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Base131072Bytes(pub Vec<u8>);
+
+impl Base131072Bytes {
+    /// Unwrap the bytes
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for Base131072Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DerefMut for Base131072Bytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for Base131072Bytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsMut<[u8]> for Base131072Bytes {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl Borrow<[u8]> for Base131072Bytes {
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl BorrowMut<[u8]> for Base131072Bytes {
+    fn borrow_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl From<Vec<u8>> for Base131072Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&[u8]> for Base131072Bytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl From<Base131072Bytes> for Vec<u8> {
+    fn from(bytes: Base131072Bytes) -> Self {
+        bytes.0
+    }
+}
+
+impl FromStr for Base131072Bytes {
+    type Err = InvalidChar;
+
+    fn from_str(string: &str) -> Result<Self, InvalidChar> {
+        decode(string).map(Self)
+    }
+}
+
+impl fmt::Display for Base131072Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode(&self.0))
+    }
+}
+
+/// A fixed number of bytes which are parsed from and displayed as a base131072 string
+///
+/// Parsing fails with [`DecodeError::WrongLength`] if the string does not decode to exactly `N`
+/// bytes.
+///
+/// ```
+/// use base131072::{Base131072Array, DecodeError};
+///
+/// let key = Base131072Array([7; 32]);
+/// let string = key.to_string();
+/// assert_eq!(string.parse::<Base131072Array<32>>().unwrap(), key);
+/// assert_eq!(
+///     string.parse::<Base131072Array<16>>(),
+///     Err(DecodeError::WrongLength { expected: 16, actual: 32 })
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Base131072Array<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Base131072Array<N> {
+    /// Unwrap the bytes
+    pub const fn into_array(self) -> [u8; N] {
+        self.0
+    }
+}
+
+impl<const N: usize> Default for Base131072Array<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> Deref for Base131072Array<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for Base131072Array<N> {
+    fn deref_mut(&mut self) -> &mut [u8; N] {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for Base131072Array<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> AsMut<[u8]> for Base131072Array<N> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Base131072Array<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const N: usize> From<Base131072Array<N>> for [u8; N] {
+    fn from(bytes: Base131072Array<N>) -> Self {
+        bytes.0
+    }
+}
+
+impl<const N: usize> FromStr for Base131072Array<N> {
+    type Err = DecodeError;
+
+    fn from_str(string: &str) -> Result<Self, DecodeError> {
+        decode_to_array(string).map(Self)
+    }
+}
+
+impl<const N: usize> fmt::Display for Base131072Array<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode(self.0))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{Base131072Array, Base131072Bytes};
+
+    impl Serialize for Base131072Bytes {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            crate::serde::serialize(&self.0, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Base131072Bytes {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            crate::serde::deserialize(deserializer).map(Self)
+        }
+    }
+
+    impl<const N: usize> Serialize for Base131072Array<N> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            crate::serde::serialize(&self.0, serializer)
+        }
+    }
+
+    impl<'de, const N: usize> Deserialize<'de> for Base131072Array<N> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            crate::serde::deserialize(deserializer).map(Self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn bytes() {
+        for len in 0..40 {
+            let bytes = Base131072Bytes((0..len).map(|idx| idx as u8).collect());
+            let string = bytes.to_string();
+            assert_eq!(string, encode(&bytes));
+            assert_eq!(string.parse(), Ok(bytes.clone()));
+            assert_eq!(bytes.len(), len);
+        }
+        let mut set = HashSet::new();
+        set.insert(Base131072Bytes::from(&b"key"[..]));
+        assert!(set.contains(&b"key"[..]));
+        assert!(Base131072Bytes::from(vec![1]) < Base131072Bytes::from(vec![1, 0]));
+        assert_eq!("\n".parse::<Base131072Bytes>(), Err(InvalidChar(0, '\n')));
+    }
+
+    #[test]
+    fn array() {
+        let array = Base131072Array(*b"0123456789abcdef");
+        let string = array.to_string();
+        assert_eq!(string.parse(), Ok(array));
+        assert_eq!(
+            string.parse::<Base131072Array<17>>(),
+            Err(DecodeError::WrongLength {
+                expected: 17,
+                actual: 16
+            })
+        );
+        assert_eq!(
+            "".parse::<Base131072Array<1>>(),
+            Err(DecodeError::WrongLength {
+                expected: 1,
+                actual: 0
+            })
+        );
+        assert_eq!("".parse(), Ok(Base131072Array([])));
+        assert_eq!(
+            "\n".parse::<Base131072Array<0>>(),
+            Err(DecodeError::InvalidChar(InvalidChar(0, '\n')))
+        );
+        assert_eq!(Base131072Array::<64>::default().into_array(), [0; 64]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde_test::{assert_tokens, Configure, Token};
+
+        let bytes = Base131072Bytes(b"bytes".to_vec());
+        let string = Box::leak(bytes.to_string().into_boxed_str());
+        assert_tokens(&bytes.clone().readable(), &[Token::Str(string)]);
+        assert_tokens(&bytes.compact(), &[Token::Bytes(b"bytes")]);
+        let array = Base131072Array(*b"array");
+        assert_tokens(&array.compact(), &[Token::Bytes(b"array")]);
+        assert_eq!(
+            crate::from_str::<Base131072Array<5>>(&crate::to_string(&array).unwrap()),
+            Ok(array)
+        );
+    }
+}